@@ -0,0 +1,286 @@
+use crate::category::Category;
+use crate::entry::Entry;
+use crate::stats::percentile;
+use crate::subscription::{normalize, RecurringRule, RuleStatus, Subscription};
+use chrono::{Duration, NaiveDate};
+use std::collections::{BTreeMap, HashMap};
+
+/// How many spreads (median absolute deviations) above its category's median an entry has to be to stand out
+const OUTLIER_SPREADS: f32 = 5.0;
+/// Categories with fewer entries than this don't have enough of a history for any of them to stand out
+const MIN_HISTORY: usize = 8;
+/// Charges with the same name and cost this many days apart or less look like duplicates
+const DUPLICATE_DAYS: i64 = 3;
+/// A repeating charge that costs this much more than it had been, as a fraction, jumped in price
+const PRICE_JUMP: f32 = 0.1;
+/// How many days late a subscription's charge can be before it's missing
+const MISSING_DAYS: i64 = 5;
+
+/// Why an entry was flagged
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq)]
+pub enum FlagKind {
+    /// It costs far more than entries in its category usually do. `typical` is their median
+    Unusual { typical: f32 },
+    /// It has the same name and cost as the entry on `original`, only a few days earlier
+    Duplicate { original: NaiveDate },
+    /// It's a charge that kept costing `previous`, but now costs more
+    PriceJump { previous: f32 },
+    /// It's the latest charge of a subscription, and the next one was due on `expected` but hasn't come
+    Missing { expected: NaiveDate },
+}
+
+/// An entry that might be a typo, a double charge or fraud
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq)]
+pub struct Flag {
+    pub entry: Entry,
+    pub kind: FlagKind,
+}
+
+impl std::fmt::Display for Flag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let cost = f32::from(self.entry.cost);
+        match self.kind {
+            FlagKind::Unusual { typical } => write!(
+                f,
+                "Unusual {}: ${:.2} is far more than the usual ${:.2}",
+                self.entry.category, cost, typical
+            ),
+            FlagKind::Duplicate { original } => {
+                write!(f, "Possible duplicate of the ${:.2} on {}", cost, original)
+            }
+            FlagKind::PriceJump { previous } => write!(
+                f,
+                "Price went up from ${:.2} to ${:.2} ({:+.0}%)",
+                previous,
+                cost,
+                (cost - previous) / previous * 100.0
+            ),
+            FlagKind::Missing { expected } => write!(
+                f,
+                "Missing charge: {} was due on {}",
+                self.entry.name, expected
+            ),
+        }
+    }
+}
+
+/// Look through `entries` (in date order) for ones that don't fit: far outside their category's usual costs,
+/// repeats of a charge a few days apart, and repeating charges that suddenly cost more. Flags are in date order
+pub fn scan<'a>(entries: impl IntoIterator<Item = &'a Entry> + Clone) -> Vec<Flag> {
+    let mut flags = unusual(entries.clone());
+    flags.extend(duplicates(entries.clone()));
+    flags.extend(price_jumps(entries));
+    flags.sort_by_key(|flag| flag.entry.date);
+    flags
+}
+
+/// The latest charge of each of `subscriptions` whose next one is more than `MISSING_DAYS` overdue by `today`.
+/// Only subscriptions confirmed in `rules`, or ones whose charge was due less than a cycle ago, are checked; one
+/// that stopped long ago and was never confirmed has most likely just ended
+pub fn missing_charges<'a, I>(
+    entries: I,
+    subscriptions: &[Subscription],
+    rules: &[RecurringRule],
+    today: NaiveDate,
+) -> Vec<Flag>
+where
+    I: IntoIterator<Item = &'a Entry> + Clone,
+    I::IntoIter: DoubleEndedIterator,
+{
+    subscriptions
+        .iter()
+        .filter(|subscription| subscription.next_expected() + Duration::days(MISSING_DAYS) < today)
+        .filter(|subscription| {
+            let confirmed = rules
+                .iter()
+                .any(|rule| rule.status == RuleStatus::Active && rule.is_for(subscription));
+            confirmed || subscription.cadence.next(subscription.next_expected()) >= today
+        })
+        .filter_map(|subscription| {
+            let last = entries
+                .clone()
+                .into_iter()
+                .rev()
+                .find(|entry| subscription.matches(entry))?;
+            Some(Flag {
+                entry: last.clone(),
+                kind: FlagKind::Missing {
+                    expected: subscription.next_expected(),
+                },
+            })
+        })
+        .collect()
+}
+
+/// Entries more than `OUTLIER_SPREADS` median absolute deviations above their category's median. A category where
+/// most entries cost the same has no spread, so a tenth of the median is used instead
+fn unusual<'a>(entries: impl IntoIterator<Item = &'a Entry> + Clone) -> Vec<Flag> {
+    let mut costs: BTreeMap<Category, Vec<f32>> = BTreeMap::new();
+    for entry in entries.clone() {
+        costs
+            .entry(entry.category)
+            .or_default()
+            .push(f32::from(entry.cost));
+    }
+    let cutoffs: BTreeMap<Category, (f32, f32)> = costs
+        .into_iter()
+        .filter(|(_, costs)| costs.len() >= MIN_HISTORY)
+        .map(|(category, mut costs)| {
+            costs.sort_by(f32::total_cmp);
+            let median = percentile(&costs, 50.0);
+            let mut deviations: Vec<f32> = costs.iter().map(|cost| (cost - median).abs()).collect();
+            deviations.sort_by(f32::total_cmp);
+            let spread = percentile(&deviations, 50.0).max(median / 10.0);
+            (category, (median, median + OUTLIER_SPREADS * spread))
+        })
+        .collect();
+
+    entries
+        .into_iter()
+        .filter_map(|entry| {
+            let &(typical, cutoff) = cutoffs.get(&entry.category)?;
+            (f32::from(entry.cost) > cutoff).then(|| Flag {
+                entry: entry.clone(),
+                kind: FlagKind::Unusual { typical },
+            })
+        })
+        .collect()
+}
+
+/// Entries with the same (normalized) name, category and cost as one up to `DUPLICATE_DAYS` before them
+fn duplicates<'a>(entries: impl IntoIterator<Item = &'a Entry>) -> Vec<Flag> {
+    // the last day each charge was seen
+    let mut seen: HashMap<(String, Category, u32), NaiveDate> = HashMap::new();
+    let mut flags = vec![];
+    for entry in entries {
+        let key = (
+            normalize(&entry.name),
+            entry.category,
+            f32::from(entry.cost).to_bits(),
+        );
+        if let Some(&original) = seen.get(&key) {
+            if (entry.date - original).num_days() <= DUPLICATE_DAYS {
+                flags.push(Flag {
+                    entry: entry.clone(),
+                    kind: FlagKind::Duplicate { original },
+                });
+            }
+        }
+        seen.insert(key, entry.date);
+    }
+    flags
+}
+
+/// Charges that cost the same the last two times they came, but more than `PRICE_JUMP` more this time
+fn price_jumps<'a>(entries: impl IntoIterator<Item = &'a Entry>) -> Vec<Flag> {
+    // the last two costs of each charge, oldest first
+    let mut history: HashMap<(String, Category), Vec<f32>> = HashMap::new();
+    let mut flags = vec![];
+    for entry in entries {
+        let cost = f32::from(entry.cost);
+        let costs = history
+            .entry((normalize(&entry.name), entry.category))
+            .or_default();
+        if let [before, previous] = costs[..] {
+            if before == previous && previous > 0.0 && cost > previous * (1.0 + PRICE_JUMP) {
+                flags.push(Flag {
+                    entry: entry.clone(),
+                    kind: FlagKind::PriceJump { previous },
+                });
+            }
+        }
+        costs.push(cost);
+        if costs.len() > 2 {
+            costs.remove(0);
+        }
+    }
+    flags
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entry::Cost;
+
+    #[test]
+    fn test_scan() {
+        let mut entries = vec![];
+        let mut add = |name: &str, cost: f32, (month, day), category| {
+            entries.push(Entry {
+                name: name.to_string(),
+                cost: Cost::try_from(cost).unwrap(),
+                date: NaiveDate::from_ymd_opt(2023, month, day).unwrap(),
+                category,
+                ..Default::default()
+            })
+        };
+        for month in 1..=10 {
+            add("Aldi", 40.0 + month as f32, (month, 2), Category::Groceries);
+        }
+        for month in 1..=3 {
+            add("Netflix", 15.0, (month, 5), Category::Subscriptions);
+        }
+        add("NETFLIX #4921", 18.0, (4, 5), Category::Subscriptions);
+        add("Aldi", 400.0, (10, 20), Category::Groceries);
+        add("Gas", 35.0, (5, 1), Category::Car);
+        add("GAS #0042", 35.0, (5, 3), Category::Car);
+        entries.sort_by_key(|entry| entry.date);
+
+        let flags: Vec<(String, FlagKind)> = scan(&entries)
+            .into_iter()
+            .map(|flag| (flag.entry.name, flag.kind))
+            .collect();
+        let date = |month, day| NaiveDate::from_ymd_opt(2023, month, day).unwrap();
+        assert_eq!(
+            flags,
+            vec![
+                (
+                    "NETFLIX #4921".to_string(),
+                    FlagKind::PriceJump { previous: 15.0 }
+                ),
+                (
+                    "GAS #0042".to_string(),
+                    FlagKind::Duplicate {
+                        original: date(5, 1)
+                    }
+                ),
+                ("Aldi".to_string(), FlagKind::Unusual { typical: 46.0 }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_missing_charges() {
+        let date = |month, day| NaiveDate::from_ymd_opt(2023, month, day).unwrap();
+        let entries: Vec<Entry> = (1..=3)
+            .map(|month| Entry {
+                name: "Spotify".to_string(),
+                cost: Cost::try_from(10.0).unwrap(),
+                date: date(month, 10),
+                category: Category::Subscriptions,
+                ..Default::default()
+            })
+            .collect();
+        let subscriptions = crate::subscription::detect(&entries);
+
+        // april's charge is only a few days late
+        assert!(missing_charges(&entries, &subscriptions, &[], date(4, 14)).is_empty());
+        let flags = missing_charges(&entries, &subscriptions, &[], date(4, 20));
+        assert_eq!(flags.len(), 1);
+        assert_eq!(flags[0].entry.date, date(3, 10));
+        assert_eq!(
+            flags[0].kind,
+            FlagKind::Missing {
+                expected: date(4, 10)
+            }
+        );
+
+        // more than a cycle later it has probably ended, unless it was confirmed
+        assert!(missing_charges(&entries, &subscriptions, &[], date(5, 20)).is_empty());
+        let rules = [RecurringRule::new(&subscriptions[0], RuleStatus::Active)];
+        assert_eq!(
+            missing_charges(&entries, &subscriptions, &rules, date(5, 20)).len(),
+            1
+        );
+    }
+}
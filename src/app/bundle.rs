@@ -0,0 +1,157 @@
+use super::components::{
+    Attention, Budget, Entries, GraphSettings, Journal, Limits, Subscriptions,
+};
+use super::App;
+use crate::csvformat::CsvFormat;
+use crate::entry::Entry;
+use crate::payperiod::PaySchedule;
+
+use std::error::Error;
+
+/// Bump this whenever a change to `Bundle` would make older builds misread it. Adding a field doesn't count;
+/// missing fields get their defaults and unknown ones are ignored
+pub const BUNDLE_VERSION: u32 = 1;
+
+/// Everything needed to move PennyPilot to another machine: the entries plus all of the settings a csv file
+/// can't hold
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)] // if we add new fields, give them default values when deserializing old state
+pub struct Bundle {
+    pub version: u32,
+    pub entries: Vec<Entry>,
+    pub spending_limits: Limits,
+    pub budget: Budget,
+    pub graph_settings: GraphSettings,
+    pub entry_view: Entries,
+    pub journal: Journal,
+    pub csv_format: Option<CsvFormat>,
+    pub pay_schedule: PaySchedule,
+    pub subscriptions: Subscriptions,
+    pub attention: Attention,
+}
+
+impl Default for Bundle {
+    fn default() -> Self {
+        Self {
+            version: BUNDLE_VERSION,
+            entries: vec![],
+            spending_limits: Limits::default(),
+            budget: Budget::default(),
+            graph_settings: GraphSettings::default(),
+            entry_view: Entries::default(),
+            journal: Journal::default(),
+            csv_format: None,
+            pay_schedule: PaySchedule::default(),
+            subscriptions: Subscriptions::default(),
+            attention: Attention::default(),
+        }
+    }
+}
+
+impl Bundle {
+    pub fn to_json(&self) -> Result<String, Box<dyn Error>> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+        let bundle: Bundle = serde_json::from_str(json)?;
+
+        if bundle.version > BUNDLE_VERSION {
+            return Err(format!(
+                "Bundle version {} is newer than this version of PennyPilot supports ({})",
+                bundle.version, BUNDLE_VERSION
+            )
+            .into());
+        }
+        // costs are only validated when they're created, which deserializing skips
+        if let Some(entry) = bundle.entries.iter().find(|e| f32::from(e.cost) < 0.0) {
+            return Err(format!("Bundle has an entry with a negative cost: {}", entry).into());
+        }
+
+        Ok(bundle)
+    }
+}
+
+impl App {
+    /// Gather the entries and settings into a bundle for exporting
+    pub fn bundle(&self) -> Bundle {
+        Bundle {
+            version: BUNDLE_VERSION,
+            entries: self.data_mgr.entries.entries().cloned().collect(),
+            spending_limits: self.spending_limits.clone(),
+            budget: self.budget.clone(),
+            graph_settings: self.graph.settings.clone(),
+            entry_view: self.entry_view.clone(),
+            journal: self.journal.clone(),
+            csv_format: self.data_mgr.csv_format,
+            pay_schedule: self.pay_schedule.schedule.clone(),
+            subscriptions: self.subscriptions.clone(),
+            attention: self.attention.clone(),
+        }
+    }
+
+    /// Replace the entries and settings with the ones in `bundle`
+    pub fn import_bundle(&mut self, bundle: Bundle) {
+        debug!(
+            "Importing bundle v{} with {} entries",
+            bundle.version,
+            bundle.entries.len()
+        );
+        self.spending_limits = bundle.spending_limits;
+        self.budget = bundle.budget;
+        self.graph.settings = bundle.graph_settings;
+        self.journal = bundle.journal;
+        self.data_mgr.csv_format = bundle.csv_format;
+        self.pay_schedule.schedule = bundle.pay_schedule;
+        self.subscriptions = bundle.subscriptions;
+        self.attention = bundle.attention;
+
+        // the entry view and the backend have to agree on how entries are sorted
+        self.data_mgr.sort_by = bundle.entry_view.sort_by;
+        self.entry_view = bundle.entry_view;
+        self.data_mgr.load_entries(bundle.entries);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::category::Category;
+    use crate::entry::Cost;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_round_trip() {
+        let bundle = Bundle {
+            entries: vec![Entry {
+                name: "Rent".to_string(),
+                cost: Cost::try_from(1500.0).unwrap(),
+                date: NaiveDate::from_ymd_opt(2023, 9, 1).unwrap(),
+                category: Category::Rent,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let json = bundle.to_json().unwrap();
+        let read = Bundle::from_json(&json).unwrap();
+        assert_eq!(read.version, BUNDLE_VERSION);
+        assert_eq!(read.entries, bundle.entries);
+        // settings are stored in hash maps, so compare values rather than strings
+        let value = |json: &str| serde_json::from_str::<serde_json::Value>(json).unwrap();
+        assert_eq!(value(&read.to_json().unwrap()), value(&json));
+    }
+
+    #[test]
+    fn test_versions() {
+        // missing fields are fine
+        let read = Bundle::from_json(r#"{"version": 1, "entries": []}"#).unwrap();
+        assert!(read.csv_format.is_none());
+
+        let newer = format!(r#"{{"version": {}}}"#, BUNDLE_VERSION + 1);
+        assert!(Bundle::from_json(&newer).is_err());
+
+        let negative = r#"{"entries": [{"name": "x", "cost": -1.0, "date": "2023-01-01", "category": "Misc"}]}"#;
+        assert!(Bundle::from_json(negative).is_err());
+    }
+}
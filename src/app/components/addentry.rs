@@ -77,6 +77,7 @@ impl AddEntry {
             cost: Cost::try_from(self.cost).unwrap(),
             date: self.date,
             category: self.category,
+            ..Default::default()
         }
     }
 }
@@ -0,0 +1,180 @@
+use super::DrillDown;
+use crate::category::Category;
+use crate::entry::Entry;
+use crate::limit::LimitStatus;
+use crate::query::Query;
+use egui::{Align2, Color32, Context, RichText, Ui};
+
+/// How long a toast stays on screen, in seconds
+const TOAST_SECONDS: f64 = 6.0;
+
+/// How close a category is to its spending limit. Ordered from least to most severe
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub enum AlertLevel {
+    /// Spending is on track to exceed the limit by the end of the period
+    Projected,
+    /// Spending passed this percentage of the limit
+    Approaching(f32),
+    Reached,
+    Exceeded,
+}
+
+impl std::fmt::Display for AlertLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            AlertLevel::Projected => write!(f, "Projected to exceed limit"),
+            AlertLevel::Approaching(percent) => write!(f, "{}% of limit", percent),
+            AlertLevel::Reached => write!(f, "Limit reached"),
+            AlertLevel::Exceeded => write!(f, "Limit exceeded"),
+        }
+    }
+}
+
+impl AlertLevel {
+    pub fn color(&self) -> Color32 {
+        match *self {
+            AlertLevel::Projected => Color32::LIGHT_YELLOW,
+            AlertLevel::Approaching(_) => Color32::YELLOW,
+            AlertLevel::Reached => Color32::GOLD,
+            AlertLevel::Exceeded => Color32::RED,
+        }
+    }
+}
+
+/// A spending limit that was approached, reached or exceeded
+#[derive(Debug, Clone)]
+pub struct Alert {
+    pub category: Category,
+    /// Spending during the limit's period, including `entry`
+    pub status: LimitStatus,
+    pub level: AlertLevel,
+    /// The entry whose addition raised the alert
+    pub entry: Entry,
+    /// Every entry that adds up to the status's `spent`
+    pub entries: Vec<Entry>,
+}
+
+impl std::fmt::Display for Alert {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {} ({})", self.level, self.category, self.status)
+    }
+}
+
+/// Alerts raised while the app has been open. Each new one pops up as a toast, and they're all kept in a panel
+/// until dismissed
+#[derive(Default)]
+pub struct Alerts {
+    // newest last, along with the ui time they were raised at
+    alerts: Vec<(Alert, f64)>,
+}
+
+impl Alerts {
+    pub fn raise(&mut self, alert: Alert, time: f64) {
+        info!("Spending alert: {}", alert);
+        self.alerts.push((alert, time));
+    }
+
+    pub fn count(&self) -> usize {
+        self.alerts.len()
+    }
+
+    /// Show toasts for recently raised alerts in the bottom right corner. Returns true if one was clicked,
+    /// meaning the alerts panel should be opened
+    pub fn toasts(&self, ctx: &Context) -> bool {
+        let now = ctx.input(|i| i.time);
+        let recent: Vec<&Alert> = self
+            .alerts
+            .iter()
+            .filter(|(_, time)| now - time < TOAST_SECONDS)
+            .map(|(alert, _)| alert)
+            .collect();
+        if recent.is_empty() {
+            return false;
+        }
+        // keep repainting so toasts go away on time even without user input
+        ctx.request_repaint_after(std::time::Duration::from_millis(500));
+
+        let mut clicked = false;
+        egui::Area::new("alert-toasts")
+            .anchor(Align2::RIGHT_BOTTOM, [-10.0, -10.0])
+            .show(ctx, |ui| {
+                for alert in recent.iter().rev() {
+                    egui::Frame::popup(ui.style()).show(ui, |ui| {
+                        let text = RichText::new(alert.to_string()).color(alert.level.color());
+                        if ui
+                            .add(egui::Label::new(text).sense(egui::Sense::click()))
+                            .on_hover_text("Click to see the entries that caused this")
+                            .clicked()
+                        {
+                            clicked = true;
+                        }
+                    });
+                }
+            });
+        clicked
+    }
+
+    /// The alerts panel. Each alert lists the entries behind it, with the one that raised it highlighted. Returns
+    /// the entries of an alert's category and limit period if they were asked for
+    pub fn ui(&mut self, ui: &mut Ui) -> Option<DrillDown> {
+        if self.alerts.is_empty() {
+            ui.label("(No Alerts)");
+            return None;
+        }
+        if ui.button("Dismiss All").clicked() {
+            self.alerts.clear();
+            return None;
+        }
+
+        let mut dismissed = None;
+        let mut drill_down = None;
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for (index, (alert, time)) in self.alerts.iter().enumerate().rev() {
+                ui.horizontal(|ui| {
+                    ui.label(RichText::new(alert.to_string()).color(alert.level.color()));
+                    if ui
+                        .button("Show")
+                        .on_hover_text("List the category's entries during the limit's period")
+                        .clicked()
+                    {
+                        drill_down = Some(Self::drill_down(alert));
+                    }
+                    if ui.button("Dismiss").clicked() {
+                        dismissed = Some(index);
+                    }
+                });
+                egui::CollapsingHeader::new(format!("{} entries", alert.entries.len()))
+                    .id_source(("alert", index, time.to_bits()))
+                    .show(ui, |ui| {
+                        for entry in &alert.entries {
+                            let text = RichText::new(format!(
+                                "{}: {} (${:.2})",
+                                entry.date,
+                                entry.name,
+                                f32::from(entry.cost)
+                            ));
+                            ui.label(if *entry == alert.entry {
+                                text.strong()
+                            } else {
+                                text
+                            });
+                        }
+                    });
+                ui.separator();
+            }
+        });
+        if let Some(index) = dismissed {
+            self.alerts.remove(index);
+        }
+        drill_down
+    }
+
+    /// Every entry in `alert`'s category during the limit period it was raised in
+    fn drill_down(alert: &Alert) -> DrillDown {
+        let LimitStatus { start, end, .. } = alert.status;
+        DrillDown::new(
+            format!("{}, {} to {}", alert.category, start, end),
+            Query::default().category(alert.category).within(start, end),
+        )
+    }
+}
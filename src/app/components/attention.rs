@@ -0,0 +1,111 @@
+use super::Subscriptions;
+use crate::anomaly::{Flag, FlagKind};
+use crate::backend::DataManager;
+use crate::entry::Entry;
+use crate::subscription::RecurringRule;
+use egui::{RichText, Ui};
+
+/// Entries that look like typos, double charges or fraud. Each one can be dismissed, or found in the entry view to
+/// investigate it
+#[derive(serde::Deserialize, serde::Serialize, Clone, Default)]
+#[serde(default)] // if we add new fields, give them default values when deserializing old state
+pub struct Attention {
+    // flags the user has dealt with, so they don't come back on the next scan
+    dismissed: Vec<Flag>,
+
+    // what the last scan found, and the data manager's change count and the recurring rules then. Entries are
+    // scanned again when either changes
+    #[serde(skip)]
+    flags: Vec<Flag>,
+    #[serde(skip)]
+    scanned: Option<u64>,
+    #[serde(skip)]
+    scanned_rules: Vec<RecurringRule>,
+}
+
+impl Attention {
+    /// List every flag that hasn't been dismissed. Missing charges of subscriptions marked as cancelled aren't
+    /// listed either. Returns the entry to show in the entry view, if one was picked
+    pub fn ui(
+        &mut self,
+        ui: &mut Ui,
+        data_mgr: &DataManager,
+        subscriptions: &Subscriptions,
+    ) -> Option<Entry> {
+        if self.scanned != Some(data_mgr.changes()) || self.scanned_rules != subscriptions.rules() {
+            self.rescan(data_mgr, subscriptions);
+        }
+
+        ui.horizontal(|ui| {
+            if ui
+                .button("Rescan")
+                .on_hover_text("Look through every entry again")
+                .clicked()
+            {
+                self.rescan(data_mgr, subscriptions);
+            }
+            if !self.dismissed.is_empty()
+                && ui
+                    .button(format!("Restore Dismissed ({})", self.dismissed.len()))
+                    .clicked()
+            {
+                self.dismissed.clear();
+            }
+        });
+        ui.separator();
+
+        let flags: Vec<&Flag> = self
+            .flags
+            .iter()
+            .filter(|flag| !self.dismissed.contains(flag))
+            .filter(|flag| {
+                !matches!(flag.kind, FlagKind::Missing { .. })
+                    || !subscriptions.is_cancelled(&flag.entry)
+            })
+            .collect();
+        if flags.is_empty() {
+            ui.label("(Nothing needs attention)");
+            return None;
+        }
+
+        let mut selected = None;
+        let mut dismissed = None;
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for flag in flags {
+                ui.label(RichText::new(flag.to_string()).strong());
+                ui.horizontal(|ui| {
+                    let entry = &flag.entry;
+                    ui.label(format!(
+                        "{}: {} (${:.2})",
+                        entry.date,
+                        entry.name,
+                        f32::from(entry.cost)
+                    ));
+                    if ui
+                        .small_button("Investigate")
+                        .on_hover_text("Find it in the entry view")
+                        .clicked()
+                    {
+                        selected = Some(entry.clone());
+                    }
+                    if ui.small_button("Dismiss").clicked() {
+                        dismissed = Some(flag.clone());
+                    }
+                });
+                ui.separator();
+            }
+        });
+        if let Some(flag) = dismissed {
+            self.dismissed.push(flag);
+        }
+        selected
+    }
+
+    fn rescan(&mut self, data_mgr: &DataManager, subscriptions: &Subscriptions) {
+        let rules = subscriptions.rules();
+        self.flags = data_mgr.anomalies(chrono::Local::now().date_naive(), rules);
+        self.scanned = Some(data_mgr.changes());
+        self.scanned_rules = rules.to_vec();
+        debug!("Found {} entries that need attention", self.flags.len());
+    }
+}
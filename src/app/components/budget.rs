@@ -0,0 +1,211 @@
+use super::Limits;
+use crate::backend::DataManager;
+use crate::budget::{Envelope, EnvelopeBudget, Transfer};
+use crate::category::Category;
+use crate::organize::GroupBy;
+use chrono::{Months, NaiveDate};
+use egui::{Color32, RichText, Ui};
+use std::collections::BTreeMap;
+
+/// Envelope budget view: what each category was allocated, spent and has left for a month
+#[derive(serde::Deserialize, serde::Serialize, Clone)]
+#[serde(default)] // if we add new fields, give them default values when deserializing old state
+pub struct Budget {
+    pub envelopes: EnvelopeBudget,
+
+    // the month being viewed
+    #[serde(skip)]
+    month: NaiveDate,
+
+    // the transfer being filled in
+    #[serde(skip)]
+    from: Category,
+    #[serde(skip)]
+    to: Category,
+    #[serde(skip)]
+    amount: f32,
+
+    // dropped when the budget itself changes
+    #[serde(skip)]
+    cached: Option<CachedEnvelopes>,
+}
+
+/// The envelopes last worked out, along with the month, allocations and data manager's change count they were for
+#[derive(Clone)]
+struct CachedEnvelopes {
+    changes: u64,
+    month: NaiveDate,
+    allocations: BTreeMap<Category, f32>,
+    envelopes: Vec<Envelope>,
+}
+
+impl Default for Budget {
+    fn default() -> Self {
+        Self {
+            envelopes: EnvelopeBudget::default(),
+            month: GroupBy::Month.start_of(chrono::Local::now().date_naive()),
+            from: Category::Misc,
+            to: Category::Groceries,
+            amount: 0.0,
+            cached: None,
+        }
+    }
+}
+
+impl Budget {
+    pub fn ui(&mut self, ui: &mut Ui, data_mgr: &DataManager, limits: &Limits) {
+        let allocations = limits.monthly_allocations();
+        if allocations.is_empty() {
+            ui.label("Set monthly spending limits to give categories an envelope");
+            return;
+        }
+
+        self.month_selector(ui);
+        let fresh = self.cached.as_ref().map_or(false, |cached| {
+            cached.changes == data_mgr.changes()
+                && cached.month == self.month
+                && cached.allocations == allocations
+        });
+        if !fresh {
+            self.cached = Some(CachedEnvelopes {
+                changes: data_mgr.changes(),
+                month: self.month,
+                allocations: allocations.clone(),
+                envelopes: self.envelopes.envelopes(&allocations, data_mgr, self.month),
+            });
+        }
+        if let Some(cached) = &self.cached {
+            Self::envelope_grid(ui, &cached.envelopes);
+        }
+        ui.separator();
+        self.transfers(ui, &allocations);
+    }
+
+    fn month_selector(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            if ui.button("<").clicked() {
+                self.month = self.month - Months::new(1);
+            }
+            ui.label(self.month.format("%B %Y").to_string());
+            if ui.button(">").clicked() {
+                self.month = self.month + Months::new(1);
+            }
+
+            ui.label(format!(
+                "Rolling over since {}",
+                self.envelopes.start.format("%B %Y")
+            ));
+            if ui
+                .add_enabled(
+                    self.envelopes.start != self.month,
+                    egui::Button::new("Start Here"),
+                )
+                .on_hover_text("Only roll over what's left from this month on")
+                .clicked()
+            {
+                self.envelopes.start = self.month;
+                self.cached = None;
+            }
+        });
+    }
+
+    fn envelope_grid(ui: &mut Ui, envelopes: &[Envelope]) {
+        egui::Grid::new("envelope-grid")
+            .num_columns(6)
+            .striped(true)
+            .show(ui, |ui| {
+                for heading in [
+                    "Category",
+                    "Carried",
+                    "Allocated",
+                    "Moved",
+                    "Spent",
+                    "Available",
+                ] {
+                    ui.label(RichText::new(heading).strong());
+                }
+                ui.end_row();
+
+                for envelope in envelopes {
+                    ui.label(envelope.category.to_string());
+                    for amount in [
+                        envelope.carried,
+                        envelope.allocated,
+                        envelope.moved,
+                        envelope.spent,
+                    ] {
+                        ui.label(format!("${:.2}", amount));
+                    }
+                    let available = envelope.available();
+                    let color = if available < 0.0 {
+                        Color32::RED
+                    } else {
+                        ui.visuals().text_color()
+                    };
+                    ui.label(RichText::new(format!("${:.2}", available)).color(color));
+                    ui.end_row();
+                }
+            });
+    }
+
+    /// Move money between envelopes for the viewed month, and list what's been moved so far
+    fn transfers(&mut self, ui: &mut Ui, allocations: &BTreeMap<Category, f32>) {
+        ui.horizontal(|ui| {
+            ui.label("Move");
+            ui.add(
+                egui::DragValue::new(&mut self.amount)
+                    .speed(10.0)
+                    .clamp_range(0.0..=1_000_000.0)
+                    .prefix("$"),
+            );
+            for (label, id, category) in [
+                ("from", "transfer-from", &mut self.from),
+                ("to", "transfer-to", &mut self.to),
+            ] {
+                ui.label(label);
+                egui::ComboBox::from_id_source(id)
+                    .selected_text(category.to_string())
+                    .show_ui(ui, |ui| {
+                        for &option in allocations.keys() {
+                            ui.selectable_value(category, option, option.to_string());
+                        }
+                    });
+            }
+
+            let valid = self.amount > 0.0
+                && self.from != self.to
+                && allocations.contains_key(&self.from)
+                && allocations.contains_key(&self.to);
+            if ui.add_enabled(valid, egui::Button::new("Move")).clicked() {
+                self.envelopes.transfers.push(Transfer {
+                    month: self.month,
+                    from: self.from,
+                    to: self.to,
+                    amount: self.amount,
+                });
+                self.amount = 0.0;
+                self.cached = None;
+            }
+        });
+
+        let mut to_remove = None;
+        for (index, transfer) in self.envelopes.transfers.iter().enumerate() {
+            if transfer.month != self.month {
+                continue;
+            }
+            ui.horizontal(|ui| {
+                ui.label(format!(
+                    "${:.2} from {} to {}",
+                    transfer.amount, transfer.from, transfer.to
+                ));
+                if ui.small_button("Undo").clicked() {
+                    to_remove = Some(index);
+                }
+            });
+        }
+        if let Some(index) = to_remove {
+            self.envelopes.transfers.remove(index);
+            self.cached = None;
+        }
+    }
+}
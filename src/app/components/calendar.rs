@@ -0,0 +1,134 @@
+use chrono::{Datelike, Duration, NaiveDate};
+use egui::{vec2, Align2, Color32, FontId, Pos2, Rect, Rounding, Sense, Ui};
+use std::collections::BTreeMap;
+
+// the size of a day's cell, and the gap between cells
+const CELL: f32 = 12.0;
+const GAP: f32 = 3.0;
+// room for the weekday labels on the left, and the month labels on top
+const LEFT_MARGIN: f32 = 32.0;
+const TOP_MARGIN: f32 = 16.0;
+
+// from nothing spent to the most spent on any one day
+const LEVEL_COLORS: [Color32; 5] = [
+    Color32::from_rgb(110, 118, 129),
+    Color32::from_rgb(14, 68, 41),
+    Color32::from_rgb(0, 109, 50),
+    Color32::from_rgb(38, 166, 65),
+    Color32::from_rgb(57, 211, 83),
+];
+
+/// How intensely to color a day that `cost` was spent on: 0 if nothing was, otherwise 1 through 4 depending on
+/// which quarter of `max` it falls in
+fn level(cost: f32, max: f32) -> usize {
+    if cost <= 0.0 || max <= 0.0 {
+        return 0;
+    }
+    ((cost / max * 4.0).ceil() as usize).clamp(1, 4)
+}
+
+/// The week (column) and weekday (row) of `date` in a calendar whose first week contains `first`.
+/// Weeks start on Monday
+fn cell_of(date: NaiveDate, first: NaiveDate) -> (usize, usize) {
+    let first_monday = first - Duration::days(first.weekday().num_days_from_monday() as i64);
+    let week = (date - first_monday).num_days() / 7;
+    (
+        week as usize,
+        date.weekday().num_days_from_monday() as usize,
+    )
+}
+
+/// Draw `days` as a calendar, one column per week and one cell per day, colored by how much was spent.
+/// Returns the day that was clicked, if any
+pub fn heatmap(ui: &mut Ui, days: &BTreeMap<NaiveDate, f32>) -> Option<NaiveDate> {
+    let (Some(&first), Some(&last)) = (days.keys().next(), days.keys().next_back()) else {
+        ui.label("Nothing to show");
+        return None;
+    };
+    let max = days.values().copied().fold(0.0, f32::max);
+    let weeks = cell_of(last, first).0 + 1;
+
+    let mut clicked = None;
+    egui::ScrollArea::horizontal().show(ui, |ui| {
+        let size = vec2(
+            LEFT_MARGIN + weeks as f32 * (CELL + GAP),
+            TOP_MARGIN + 7.0 * (CELL + GAP),
+        );
+        let (rect, response) = ui.allocate_exact_size(size, Sense::click());
+        let painter = ui.painter_at(rect);
+        let text_color = ui.visuals().weak_text_color();
+        let cell_rect = |date: NaiveDate| {
+            let (week, weekday) = cell_of(date, first);
+            let min = rect.min
+                + vec2(
+                    LEFT_MARGIN + week as f32 * (CELL + GAP),
+                    TOP_MARGIN + weekday as f32 * (CELL + GAP),
+                );
+            Rect::from_min_size(min, vec2(CELL, CELL))
+        };
+
+        for (row, weekday) in [(0, "Mon"), (2, "Wed"), (4, "Fri")] {
+            painter.text(
+                Pos2::new(
+                    rect.min.x,
+                    rect.min.y + TOP_MARGIN + row as f32 * (CELL + GAP) + CELL / 2.0,
+                ),
+                Align2::LEFT_CENTER,
+                weekday,
+                FontId::proportional(10.0),
+                text_color,
+            );
+        }
+
+        let hovered = response.hover_pos();
+        let mut hovered_day = None;
+        for (&date, &cost) in days {
+            let cell = cell_rect(date);
+            // label each month above the week it starts in
+            if date.day() == 1 || date == first {
+                painter.text(
+                    Pos2::new(cell.min.x, rect.min.y),
+                    Align2::LEFT_TOP,
+                    date.format("%b").to_string(),
+                    FontId::proportional(10.0),
+                    text_color,
+                );
+            }
+            painter.rect_filled(cell, Rounding::same(2.0), LEVEL_COLORS[level(cost, max)]);
+            if hovered.map_or(false, |pos| cell.contains(pos)) {
+                hovered_day = Some((date, cost));
+            }
+        }
+
+        if let Some((date, cost)) = hovered_day {
+            let response = response.on_hover_text_at_pointer(format!(
+                "{}\n${:.2}",
+                date.format("%a %Y-%m-%d"),
+                cost
+            ));
+            if response.clicked() {
+                clicked = Some(date);
+            }
+        }
+    });
+    clicked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cells() {
+        assert_eq!(level(0.0, 100.0), 0);
+        assert_eq!(level(1.0, 100.0), 1);
+        assert_eq!(level(50.0, 100.0), 2);
+        assert_eq!(level(100.0, 100.0), 4);
+
+        // 2024-01-03 is a Wednesday, so its week starts on the 1st
+        let date = |day| NaiveDate::from_ymd_opt(2024, 1, day).unwrap();
+        assert_eq!(cell_of(date(3), date(3)), (0, 2));
+        assert_eq!(cell_of(date(7), date(3)), (0, 6));
+        assert_eq!(cell_of(date(8), date(3)), (1, 0));
+    }
+}
@@ -0,0 +1,75 @@
+use crate::backend::DataManager;
+use crate::csvformat::*;
+use egui::Ui;
+use strum::IntoEnumIterator;
+
+pub struct CsvSettings {}
+
+/// Choose how dates and costs are read from and written to csv files, or let them be detected
+impl CsvSettings {
+    pub fn add(ui: &mut Ui, data_mgr: &mut DataManager) {
+        let mut auto_detect = data_mgr.csv_format.is_none();
+        if ui
+            .checkbox(&mut auto_detect, "Detect format when importing")
+            .on_hover_text("Exports use the format of the last imported file")
+            .changed()
+        {
+            data_mgr.csv_format = if auto_detect {
+                None
+            } else {
+                Some(data_mgr.export_csv_format())
+            };
+        }
+
+        match data_mgr.detected_csv_format {
+            Some(format) => ui.label(format!("Last import: {}", format)),
+            None => ui.label("Nothing imported yet"),
+        };
+
+        // editing is only possible when the format is set explicitly
+        let mut format = data_mgr.export_csv_format();
+        ui.add_enabled_ui(!auto_detect, |ui| {
+            egui::Grid::new("csv-format-grid")
+                .num_columns(2)
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.label("Dates:");
+                    egui::ComboBox::from_id_source("csv-date-format")
+                        .selected_text(format.date.to_string())
+                        .show_ui(ui, |ui| {
+                            for date in DateFormat::iter() {
+                                ui.selectable_value(&mut format.date, date, date.to_string());
+                            }
+                        });
+                    ui.end_row();
+
+                    ui.label("Costs:");
+                    egui::ComboBox::from_id_source("csv-number-format")
+                        .selected_text(format.number.to_string())
+                        .show_ui(ui, |ui| {
+                            for number in NumberFormat::iter() {
+                                ui.selectable_value(&mut format.number, number, number.to_string());
+                            }
+                        });
+                    ui.end_row();
+
+                    ui.label("Separator:");
+                    egui::ComboBox::from_id_source("csv-delimiter")
+                        .selected_text(format.delimiter.to_string())
+                        .show_ui(ui, |ui| {
+                            for delimiter in Delimiter::iter() {
+                                ui.selectable_value(
+                                    &mut format.delimiter,
+                                    delimiter,
+                                    delimiter.to_string(),
+                                );
+                            }
+                        });
+                    ui.end_row();
+                });
+        });
+        if !auto_detect {
+            data_mgr.csv_format = Some(format);
+        }
+    }
+}
@@ -0,0 +1,270 @@
+use super::GraphSettings;
+use crate::backend::DataManager;
+use crate::category::Category;
+use crate::organize::{DateRange, GroupBy};
+use crate::query::Query;
+use chrono::NaiveDate;
+use egui::{epaint::Mesh, vec2, Align2, Color32, FontId, Pos2, RichText, Sense, Shape, Ui, Vec2};
+use std::collections::BTreeMap;
+use std::f32::consts::{FRAC_PI_2, TAU};
+use strum::IntoEnumIterator;
+
+const DONUT_SIZE: f32 = 220.0;
+// the radius of the hole, as a fraction of the donut's
+const HOLE: f32 = 0.55;
+// how many triangle pairs a full turn of the donut is drawn with
+const SEGMENTS: f32 = 180.0;
+
+/// One category's share of the spending, drawn as a slice of the donut
+#[derive(Debug, PartialEq)]
+struct Slice {
+    category: Category,
+    amount: f32,
+    // where the slice starts and how much of the donut it takes up, both as fractions of a full turn
+    start: f32,
+    share: f32,
+}
+
+/// Split `totals` into slices, going clockwise in category order. Categories with nothing spent are left out
+fn slices(totals: &BTreeMap<Category, f32>) -> Vec<Slice> {
+    let total: f32 = totals.values().filter(|&&amount| amount > 0.0).sum();
+    let mut start = 0.0;
+    totals
+        .iter()
+        .filter(|(_, &amount)| amount > 0.0)
+        .map(|(&category, &amount)| {
+            let share = amount / total;
+            let slice = Slice {
+                category,
+                amount,
+                start,
+                share,
+            };
+            start += share;
+            slice
+        })
+        .collect()
+}
+
+/// The slice that covers `turn` (a fraction of a full turn, clockwise from the top)
+fn slice_at(slices: &[Slice], turn: f32) -> Option<&Slice> {
+    slices
+        .iter()
+        .find(|slice| slice.start <= turn && turn < slice.start + slice.share)
+}
+
+/// The unit vector pointing `turn` of the way around, clockwise from the top. Screen y points down
+fn direction(turn: f32) -> Vec2 {
+    let angle = turn * TAU - FRAC_PI_2;
+    vec2(angle.cos(), angle.sin())
+}
+
+/// Inverse of `direction`
+fn turn_of(offset: Vec2) -> f32 {
+    ((offset.y.atan2(offset.x) + FRAC_PI_2) / TAU).rem_euclid(1.0)
+}
+
+/// Each category's share of the spending over a period, as a donut chart. Clicking a slice (or its legend entry)
+/// toggles whether the category is graphed
+pub struct Donut {
+    date_range: DateRange,
+    // only used for DateRange::Custom
+    custom_from: NaiveDate,
+    custom_to: NaiveDate,
+}
+
+impl Default for Donut {
+    fn default() -> Self {
+        let today = chrono::Local::now().date_naive();
+        Self {
+            date_range: DateRange::ThisMonth,
+            custom_from: GroupBy::Month.start_of(today),
+            custom_to: today,
+        }
+    }
+}
+
+impl Donut {
+    /// Chart the entries matching `filter` within the selected period, colored like the graph
+    pub fn ui(
+        &mut self,
+        ui: &mut Ui,
+        data_mgr: &DataManager,
+        settings: &mut GraphSettings,
+        filter: &Query,
+    ) {
+        self.period_ui(ui);
+
+        let today = chrono::Local::now().date_naive();
+        let query = match self
+            .date_range
+            .bounds(today, (self.custom_from, self.custom_to))
+        {
+            Some((from, to)) => filter.clone().within(from, to),
+            None => filter.clone(),
+        };
+        let slices = slices(&data_mgr.query(&query).by_category());
+        if slices.is_empty() {
+            ui.label("Nothing was spent in this period");
+            return;
+        }
+
+        let mut clicked = None;
+        ui.horizontal(|ui| {
+            clicked = Self::donut(ui, &slices, settings);
+            ui.vertical(|ui| {
+                for slice in &slices {
+                    let text = RichText::new(format!(
+                        "{}: ${:.2} ({:.1}%)",
+                        slice.category,
+                        slice.amount,
+                        slice.share * 100.0
+                    ))
+                    .color(settings.category_color(slice.category));
+                    if ui
+                        .selectable_label(settings.is_selected(slice.category), text)
+                        .clicked()
+                    {
+                        clicked = Some(slice.category);
+                    }
+                }
+            });
+        });
+        if let Some(category) = clicked {
+            settings.toggle_category(category);
+        }
+    }
+
+    fn period_ui(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Period:");
+            egui::ComboBox::from_id_source("donut-range")
+                .selected_text(self.date_range.to_string())
+                .show_ui(ui, |ui| {
+                    for range in DateRange::iter() {
+                        ui.selectable_value(&mut self.date_range, range, range.to_string());
+                    }
+                });
+            if self.date_range == DateRange::Custom {
+                ui.add(
+                    egui_extras::DatePickerButton::new(&mut self.custom_from)
+                        .id_source("donut-from"),
+                );
+                ui.label("to");
+                ui.add(
+                    egui_extras::DatePickerButton::new(&mut self.custom_to).id_source("donut-to"),
+                );
+            }
+        });
+    }
+
+    /// Draw the donut, with the total in the middle. Categories that aren't graphed are faded out, and the hovered
+    /// slice sticks out a little. Returns the category of the slice that was clicked, if any
+    fn donut(ui: &mut Ui, slices: &[Slice], settings: &GraphSettings) -> Option<Category> {
+        let (rect, response) = ui.allocate_exact_size(vec2(DONUT_SIZE, DONUT_SIZE), Sense::click());
+        let center = rect.center();
+        let outer = DONUT_SIZE / 2.0;
+        let inner = outer * HOLE;
+
+        let hovered = response
+            .hover_pos()
+            .filter(|pos| (inner..=outer).contains(&(*pos - center).length()))
+            .and_then(|pos| slice_at(slices, turn_of(pos - center)));
+
+        let mut mesh = Mesh::default();
+        for slice in slices {
+            let mut color = settings.category_color(slice.category);
+            if !settings.is_selected(slice.category) {
+                color = color.gamma_multiply(0.3);
+            }
+            let radius = if hovered == Some(slice) {
+                outer
+            } else {
+                outer * 0.95
+            };
+            Self::add_slice(&mut mesh, center, (inner, radius), slice, color);
+        }
+        let painter = ui.painter_at(rect);
+        painter.add(Shape::mesh(mesh));
+        painter.text(
+            center,
+            Align2::CENTER_CENTER,
+            format!(
+                "${:.2}",
+                slices.iter().map(|slice| slice.amount).sum::<f32>()
+            ),
+            FontId::proportional(16.0),
+            ui.visuals().text_color(),
+        );
+
+        let hovered = hovered?;
+        let response = response.on_hover_text_at_pointer(format!(
+            "{}\n${:.2} ({:.1}%)\nClick to toggle it in the graph",
+            hovered.category,
+            hovered.amount,
+            hovered.share * 100.0
+        ));
+        response.clicked().then_some(hovered.category)
+    }
+
+    /// Add a ring segment between the `radii` covering `slice` to `mesh`
+    fn add_slice(mesh: &mut Mesh, center: Pos2, radii: (f32, f32), slice: &Slice, color: Color32) {
+        let (inner, outer) = radii;
+        let steps = (slice.share * SEGMENTS).ceil().max(1.0) as u32;
+        let first = mesh.vertices.len() as u32;
+        for step in 0..=steps {
+            let dir = direction(slice.start + slice.share * step as f32 / steps as f32);
+            mesh.colored_vertex(center + dir * inner, color);
+            mesh.colored_vertex(center + dir * outer, color);
+        }
+        for step in 0..steps {
+            let idx = first + step * 2;
+            mesh.add_triangle(idx, idx + 1, idx + 2);
+            mesh.add_triangle(idx + 1, idx + 3, idx + 2);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slices() {
+        let totals = BTreeMap::from([
+            (Category::Rent, 300.0),
+            (Category::Misc, 0.0),
+            (Category::Groceries, 100.0),
+        ]);
+        let slices = slices(&totals);
+        assert_eq!(
+            slices,
+            [
+                Slice {
+                    category: Category::Rent,
+                    amount: 300.0,
+                    start: 0.0,
+                    share: 0.75,
+                },
+                Slice {
+                    category: Category::Groceries,
+                    amount: 100.0,
+                    start: 0.75,
+                    share: 0.25,
+                },
+            ]
+        );
+
+        // straight down is half way around
+        let turn = turn_of(vec2(0.0, 1.0));
+        assert!((turn - 0.5).abs() < 1e-6);
+        assert_eq!(slice_at(&slices, turn).unwrap().category, Category::Rent);
+        // and straight left is three quarters
+        assert_eq!(
+            slice_at(&slices, turn_of(vec2(-1.0, -0.01)))
+                .unwrap()
+                .category,
+            Category::Groceries
+        );
+    }
+}
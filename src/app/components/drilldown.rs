@@ -0,0 +1,51 @@
+use crate::backend::DataManager;
+use crate::entry::Entry;
+use crate::query::Query;
+use egui::{RichText, Ui};
+
+/// The entries behind one segment of a bar in the graph
+pub struct DrillDown {
+    // what the segment is, e.g. "Groceries, Jan 2024"
+    pub title: String,
+    query: Query,
+}
+
+impl DrillDown {
+    pub fn new(title: String, query: Query) -> Self {
+        Self { title, query }
+    }
+
+    /// List the entries and their total. Returns the entry to show in the entry view, if one was picked
+    pub fn ui(&self, ui: &mut Ui, data_mgr: &DataManager) -> Option<Entry> {
+        let result = data_mgr.query(&self.query);
+        ui.label(RichText::new(&self.title).strong());
+        ui.label(format!(
+            "{} entries, ${:.2} total",
+            result.count(),
+            result.total()
+        ));
+        ui.separator();
+
+        let mut selected = None;
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for entry in result.entries() {
+                ui.horizontal(|ui| {
+                    ui.label(format!(
+                        "{}: {} (${:.2})",
+                        entry.date,
+                        entry.name,
+                        f32::from(entry.cost)
+                    ));
+                    if ui
+                        .small_button("Show")
+                        .on_hover_text("Find it in the entry view")
+                        .clicked()
+                    {
+                        selected = Some(entry.clone());
+                    }
+                });
+            }
+        });
+        selected
+    }
+}
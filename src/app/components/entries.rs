@@ -1,14 +1,25 @@
 use crate::backend::DataManager;
+use crate::entry::Entry;
 use crate::organize::*;
-use egui::Ui;
+use crate::query::Query;
+use egui::{Align, RichText, Ui};
 use strum::IntoEnumIterator;
 
+#[derive(serde::Deserialize, serde::Serialize, Clone)]
+#[serde(default)] // if we add new fields, give them default values when deserializing old state
 pub struct Entries {
     pub sort_by: SortBy,
     pub sort_order: SortOrder,
 
     // allow deleting entries from the view. If true, a "delete" button will be clickable next to each entry
     pub allow_deletion: bool,
+
+    // an entry picked from somewhere else (e.g. the graph) to point out, and whether it still needs scrolling to.
+    // It's kept by value rather than by position, so adding or deleting other entries doesn't move the highlight
+    #[serde(skip)]
+    pub highlighted: Option<Entry>,
+    #[serde(skip)]
+    pub scroll_to_highlighted: bool,
 }
 
 impl Default for Entries {
@@ -18,15 +29,24 @@ impl Default for Entries {
             sort_by: SortBy::Date,
             sort_order: SortOrder::Increasing,
             allow_deletion: false,
+            highlighted: None,
+            scroll_to_highlighted: false,
         }
     }
 }
 
 /// A vertical scroll area for inspecting, sorting, and deleting entry objects
 impl Entries {
-    pub fn ui(&mut self, ui: &mut Ui, data_mgr: &mut DataManager) {
+    /// Only entries matching `filter` are listed
+    pub fn ui(&mut self, ui: &mut Ui, data_mgr: &mut DataManager, filter: &Query) {
         self.controls(ui, data_mgr);
-        self.scroll_area(ui, data_mgr);
+        self.scroll_area(ui, data_mgr, filter);
+    }
+
+    /// Point out `entry` and scroll to it the next time the list is shown
+    pub fn highlight(&mut self, entry: Entry) {
+        self.highlighted = Some(entry);
+        self.scroll_to_highlighted = true;
     }
 
     fn controls(&mut self, ui: &mut Ui, data_mgr: &mut DataManager) {
@@ -62,54 +82,69 @@ impl Entries {
         });
     }
 
-    fn scroll_area(&mut self, ui: &mut Ui, data_mgr: &mut DataManager) {
+    fn scroll_area(&mut self, ui: &mut Ui, data_mgr: &mut DataManager, filter: &Query) {
+        let result = data_mgr.sorted_query(filter);
+        if *filter != Query::default() {
+            ui.label(format!(
+                "Filtered: showing {} of {} entries (${:.2})",
+                result.count(),
+                data_mgr.entries.len(),
+                result.total()
+            ));
+        }
+
+        let mut to_delete = Vec::new();
         egui::ScrollArea::vertical()
             // .max_width(400.0)
             .show(ui, |ui| {
-                if data_mgr.entries.is_empty() {
+                if result.count() == 0 {
                     ui.label("(No Entries)");
                 }
-                let mut to_delete = Vec::new();
                 let reversed = self.sort_order == SortOrder::Decreasing;
-                for (index, entry) in data_mgr.get_entries_iter(reversed).enumerate() {
+                let matches: Box<dyn Iterator<Item = _>> = if reversed {
+                    Box::new(result.iter().rev())
+                } else {
+                    Box::new(result.iter())
+                };
+                // keys stay the same until an entry is removed, so they can be deleted directly
+                for (key, entry) in matches {
                     ui.vertical(|ui| {
                         ui.horizontal(|ui| {
-                            ui.label(format!(
+                            let text = RichText::new(format!(
                                 "{}: {}, {} (${:.2})",
                                 &entry.date.to_string(),
                                 &entry.name,
                                 &entry.category,
                                 Into::<f32>::into(entry.cost),
                             ));
+                            if self.highlighted.as_ref() == Some(entry) {
+                                let label = ui.label(text.strong().underline());
+                                if std::mem::take(&mut self.scroll_to_highlighted) {
+                                    label.scroll_to_me(Some(Align::Center));
+                                }
+                            } else {
+                                ui.label(text);
+                            }
                             ui.add_enabled_ui(self.allow_deletion, |ui| {
                                 if ui.button("Delete").clicked() {
                                     // we can't delete the entry while we're iterating the entries
-                                    to_delete.push(index);
+                                    to_delete.push(key);
                                 }
                             });
                         });
                         ui.separator();
                     });
                 }
+            });
 
-                if to_delete.len() > 1 {
-                    debug!("More than 1 entry to delete this frame. That's weird");
-                }
+        if to_delete.len() > 1 {
+            debug!("More than 1 entry to delete this frame. That's weird");
+        }
 
-                // now that we're done iterating, it's safe to delete the entries. It should only be 1 unless
-                // the user is very fast or framerate very slow. Iterate backwards because each deletion would
-                // shift other indices (but again, there should only be 1)
-                for index in to_delete.iter().rev() {
-                    // if we're viewing the list reversed, we need to translate the index so that we remove the
-                    // right thing from the backend.
-                    let mut idx = *index;
-                    if self.sort_order == SortOrder::Decreasing {
-                        // it shouldn't be possible to delete an entry if there's nothing in the list
-                        // if that happens, just let it panic on the invalid index
-                        idx = data_mgr.entries.len() - 1 - idx;
-                    }
-                    data_mgr.remove_entry_pos(idx);
-                }
-            });
+        // now that we're done iterating, it's safe to delete the entries. It should only be 1 unless
+        // the user is very fast or framerate very slow
+        for key in to_delete {
+            data_mgr.remove_entry(key);
+        }
     }
 }
@@ -0,0 +1,169 @@
+use crate::backend::DataManager;
+use crate::category::Category;
+use crate::query::{NameFilter, NameRegex, Query};
+use chrono::NaiveDate;
+use egui::{Color32, RichText, Ui};
+use strum::IntoEnumIterator;
+
+/// Edit the query that filters the entry view and the graph
+#[derive(Default)]
+pub struct Filter {
+    pub query: Query,
+
+    // the name being searched for, and whether it's a regex
+    name: String,
+    regex: bool,
+    // the name and regex setting the query was last updated for, and why it couldn't be if the regex is invalid.
+    // An invalid regex leaves the query as it was
+    applied: (String, bool),
+    error: Option<String>,
+}
+
+impl Filter {
+    pub fn is_active(&self) -> bool {
+        self.query != Query::default()
+    }
+
+    pub fn ui(&mut self, ui: &mut Ui, data_mgr: &DataManager) {
+        egui::Grid::new("filter-grid")
+            .num_columns(2)
+            .striped(true)
+            .show(ui, |ui| {
+                ui.label("Name:");
+                ui.horizontal(|ui| {
+                    ui.add(egui::TextEdit::singleline(&mut self.name).hint_text("Any name"));
+                    ui.checkbox(&mut self.regex, "Regex");
+                });
+                ui.end_row();
+
+                ui.label("From:");
+                Self::date_ui(ui, &mut self.query.from, "filter-from");
+                ui.end_row();
+
+                ui.label("To:");
+                Self::date_ui(ui, &mut self.query.to, "filter-to");
+                ui.end_row();
+
+                ui.label("Cost:");
+                ui.horizontal(|ui| {
+                    Self::cost_ui(ui, &mut self.query.min_cost, "Min");
+                    Self::cost_ui(ui, &mut self.query.max_cost, "Max");
+                });
+                ui.end_row();
+            });
+        self.categories_ui(ui);
+
+        if self.applied != (self.name.clone(), self.regex) {
+            self.applied = (self.name.clone(), self.regex);
+            self.update_name();
+        }
+        if let Some(e) = &self.error {
+            ui.label(RichText::new(format!("Invalid regex: {e}")).color(Color32::RED));
+        }
+        ui.separator();
+        Self::summary(ui, data_mgr, &self.query);
+
+        if ui
+            .add_enabled(self.is_active(), egui::Button::new("Clear"))
+            .clicked()
+        {
+            *self = Filter::default();
+        }
+    }
+
+    /// Match the name being searched for. Only called when it's edited, so a regex is compiled once
+    fn update_name(&mut self) {
+        self.error = None;
+        self.query.name = match (self.name.trim(), self.regex) {
+            ("", _) => None,
+            (name, false) => Some(NameFilter::Contains(name.to_string())),
+            (name, true) => match NameRegex::new(name) {
+                Ok(regex) => Some(NameFilter::Regex(regex)),
+                Err(e) => {
+                    self.error = Some(e.to_string());
+                    return;
+                }
+            },
+        };
+    }
+
+    /// Totals for whatever currently matches
+    fn summary(ui: &mut Ui, data_mgr: &DataManager, query: &Query) {
+        let result = data_mgr.query(query);
+        let (Some(average), Some((first, last))) = (result.average(), result.date_range()) else {
+            ui.label("No entries match");
+            return;
+        };
+        ui.label(format!(
+            "{} entries from {} to {}: ${:.2} total, ${:.2} on average",
+            result.count(),
+            first,
+            last,
+            result.total(),
+            average
+        ));
+        egui::CollapsingHeader::new("By Category")
+            .default_open(false)
+            .show(ui, |ui| {
+                for (category, total) in result.by_category() {
+                    ui.label(format!("{}: ${:.2}", category, total));
+                }
+            });
+    }
+
+    /// An optional date: a checkbox to turn it on, then a picker
+    fn date_ui(ui: &mut Ui, date: &mut Option<NaiveDate>, id: &str) {
+        ui.horizontal(|ui| {
+            let mut enabled = date.is_some();
+            ui.checkbox(&mut enabled, "");
+            match (enabled, date.as_mut()) {
+                (true, Some(date)) => {
+                    ui.add(egui_extras::DatePickerButton::new(date).id_source(id));
+                }
+                (true, None) => *date = Some(chrono::Local::now().date_naive()),
+                (false, _) => *date = None,
+            }
+        });
+    }
+
+    /// An optional cost: a checkbox to turn it on, then its value
+    fn cost_ui(ui: &mut Ui, cost: &mut Option<f32>, label: &str) {
+        let mut enabled = cost.is_some();
+        ui.checkbox(&mut enabled, label);
+        if !enabled {
+            *cost = None;
+            return;
+        }
+        let value = cost.get_or_insert(0.0);
+        ui.add(
+            egui::DragValue::new(value)
+                .speed(2.5)
+                .clamp_range(0.0..=1_000_000.0)
+                .prefix("$"),
+        );
+    }
+
+    fn categories_ui(&mut self, ui: &mut Ui) {
+        egui::CollapsingHeader::new("Categories")
+            .default_open(false)
+            .show(ui, |ui| {
+                let mut any = self.query.categories.is_none();
+                ui.checkbox(&mut any, "Any Category");
+                if any {
+                    self.query.categories = None;
+                    return;
+                }
+                let categories = self.query.categories.get_or_insert_with(Vec::new);
+                for category in Category::iter().filter(|&category| category != Category::All) {
+                    let mut selected = categories.contains(&category);
+                    if ui.checkbox(&mut selected, category.to_string()).changed() {
+                        if selected {
+                            categories.push(category);
+                        } else {
+                            categories.retain(|&c| c != category);
+                        }
+                    }
+                }
+            });
+    }
+}
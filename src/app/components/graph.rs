@@ -2,115 +2,653 @@ use crate::category::*;
 use crate::colors::*;
 use crate::organize::*;
 
+use super::calendar::heatmap;
+use super::{DrillDown, Limits};
 use crate::backend::*;
+use crate::cache::CostMapView;
+use crate::payperiod::PaySchedule;
+use crate::query::Query;
+use crate::stats::change;
+use chrono::{Datelike, Months, NaiveDate, Weekday};
 use egui::CollapsingHeader;
 use egui::{
-    plot::{Bar, BarChart, Legend, Plot, PlotPoint},
-    Grid, Response, Ui,
+    plot::{
+        Bar, BarChart, GridInput, GridMark, Legend, Line, LineStyle, Plot, PlotPoint, PlotPoints,
+        Polygon,
+    },
+    Color32, Grid, RichText, Stroke, Ui,
 };
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::ops::RangeInclusive;
+use std::rc::Rc;
 use strum::IntoEnumIterator;
 
 /// In charge of plotting planner data. Stores its own settings, which can draw a ui to edit them.
+#[derive(Default)]
 pub struct Graph {
     // the graph settings window
     pub settings: GraphSettings,
 }
 
 const DATA_ASPECT: f32 = 0.5;
-impl Default for Graph {
-    fn default() -> Self {
-        let group_by = GroupBy::Month;
 
-        let (width, spacing) = get_width_spacing(group_by);
+// how far apart (in points) x axis labels have to be so they don't overlap
+const LABEL_SPACING: f64 = 90.0;
 
-        let settings = GraphSettings {
-            width,
-            spacing,
-            data_aspect: DATA_ASPECT,
-            theme: Theme::Sunset,
-            group_by,
-            category_selector: CategorySelector::new(),
+fn get_width_spacing(grouping: Grouping) -> (f64, f64) {
+    match grouping {
+        Grouping::Day => (0.7, 1.0),
+        Grouping::Week(_) => (5.0, 7.0),
+        Grouping::Month => (21.0, 30.0),
+        Grouping::Quarter => (63.0, 90.0),
+        Grouping::PayPeriod => (10.0, 14.0),
+        Grouping::Year | Grouping::FiscalYear(_) => (252.0, 360.0),
+    }
+}
+
+impl Graph {
+    /// Graph the entries matching `filter`, in whichever of its categories are selected in the settings.
+    /// Returns the entries behind a bar segment if one was clicked. Pay periods follow `pay_schedule`
+    pub fn ui(
+        &mut self,
+        ui: &mut Ui,
+        data_mgr: &mut DataManager,
+        limits: &Limits,
+        pay_schedule: &PaySchedule,
+        filter: &Query,
+    ) -> Option<DrillDown> {
+        if std::mem::take(&mut self.settings.dates_changed) {
+            data_mgr.plot_reset_next_frame = true;
+        }
+        match self.settings.mode {
+            ChartMode::Bars => {}
+            ChartMode::Pace => {
+                let pace = self.build_pace(data_mgr, limits, pay_schedule, filter);
+                self.plot_pace(ui, pace, &mut data_mgr.plot_reset_next_frame);
+                return None;
+            }
+            ChartMode::Calendar => return self.calendar(ui, data_mgr, filter),
+            ChartMode::Comparison => {
+                self.comparison(ui, data_mgr, filter);
+                return None;
+            }
+        }
+
+        let query = self.query(filter);
+        let chart = self.build_chart(data_mgr, limits, pay_schedule, &query);
+        let (category, date) = self.plot(ui, chart, &mut data_mgr.plot_reset_next_frame)?;
+
+        let group_by = self.settings.group_by(pay_schedule);
+        let (start, end) = group_by.range(date);
+        Some(DrillDown::new(
+            format!("{}, {}", category, group_by.label(date)),
+            query.category(category).within(start, end),
+        ))
+    }
+
+    /// Daily spending in one category (or all of the graphed ones) as a calendar heatmap. Returns the entries of a
+    /// day if one was clicked
+    fn calendar(
+        &mut self,
+        ui: &mut Ui,
+        data_mgr: &DataManager,
+        filter: &Query,
+    ) -> Option<DrillDown> {
+        // only graphed categories can be picked, so one that stops being graphed falls back to all of them
+        let selected = self.settings.selected_categories();
+        if !selected.contains(&self.settings.calendar_category) {
+            self.settings.calendar_category = Category::All;
+        }
+        ui.horizontal(|ui| {
+            ui.label("Category:");
+            egui::ComboBox::from_id_source("calendar-category")
+                .selected_text(self.settings.calendar_category.to_string())
+                .show_ui(ui, |ui| {
+                    let categories = Category::iter().filter(|category| {
+                        *category == Category::All || selected.contains(category)
+                    });
+                    for category in categories {
+                        ui.selectable_value(
+                            &mut self.settings.calendar_category,
+                            category,
+                            category.to_string(),
+                        );
+                    }
+                });
+        });
+
+        let query = self.query(filter).category(self.settings.calendar_category);
+        let mut days: BTreeMap<NaiveDate, f32> = BTreeMap::new();
+        for (category, inner_map) in data_mgr
+            .cost_map(CostMapView::Calendar, GroupBy::Day, &query)
+            .iter()
+        {
+            if *category == Category::All {
+                continue;
+            }
+            for (date, cost) in inner_map {
+                *days.entry(*date).or_default() += cost;
+            }
+        }
+
+        let date = heatmap(ui, &days)?;
+        Some(DrillDown::new(
+            format!("{}, {}", self.settings.calendar_category, date),
+            query.within(date, date),
+        ))
+    }
+
+    /// Spending in each month of the compared years as bars side by side, below tables of how much each month and
+    /// category changed from the year before the newest to the newest
+    fn comparison(&self, ui: &mut Ui, data_mgr: &mut DataManager, filter: &Query) {
+        let query = filter
+            .clone()
+            .categories(self.settings.selected_categories());
+        let years = self.settings.compare_years();
+        let queries: Vec<Query> = years
+            .iter()
+            .map(|&year| {
+                let (start, end) =
+                    GroupBy::Year.range(NaiveDate::from_ymd_opt(year, 1, 1).unwrap());
+                query.clone().within(start, end)
+            })
+            .collect();
+        let maps: Vec<Rc<CostMap>> =
+            data_mgr.cost_maps(CostMapView::Comparison, GroupBy::Month, &queries);
+        let months: Vec<[f32; 12]> = maps.iter().map(|map| month_totals(map)).collect();
+        let month_name = |month: usize| {
+            NaiveDate::from_ymd_opt(2000, month as u32 + 1, 1)
+                .unwrap()
+                .format("%b")
+                .to_string()
         };
 
-        Self { settings }
+        CollapsingHeader::new("Changes")
+            .default_open(false)
+            .show(ui, |ui| {
+                egui::ScrollArea::vertical()
+                    .max_height(250.0)
+                    .show(ui, |ui| {
+                        let mut rows: Vec<(String, Vec<f32>)> = (0..12)
+                            .map(|month| {
+                                let totals = months.iter().map(|totals| totals[month]).collect();
+                                (month_name(month), totals)
+                            })
+                            .collect();
+                        rows.push((
+                            "Total".to_string(),
+                            months.iter().map(|totals| totals.iter().sum()).collect(),
+                        ));
+                        Self::delta_grid(ui, "comparison-months", &years, rows);
+                        ui.separator();
+
+                        let rows = Category::iter()
+                            .filter(|&category| category != Category::All)
+                            .filter(|&category| self.settings.is_selected(category))
+                            .map(|category| {
+                                let totals = maps
+                                    .iter()
+                                    .map(|map| {
+                                        map.get(&category).map_or(0.0, |dates| dates.values().sum())
+                                    })
+                                    .collect();
+                                (category.to_string(), totals)
+                            })
+                            .collect();
+                        Self::delta_grid(ui, "comparison-categories", &years, rows);
+                    });
+            });
+
+        let width = 0.8 / years.len() as f64;
+        let colors = self.settings.theme.colors();
+        let charts: Vec<BarChart> = years
+            .iter()
+            .zip(&months)
+            .enumerate()
+            .map(|(idx, (year, totals))| {
+                // each year's bar sits beside the others in its month, centered on the month as a group
+                let offset = (idx as f64 - (years.len() - 1) as f64 / 2.0) * width;
+                let bars = totals
+                    .iter()
+                    .enumerate()
+                    .map(|(month, cost)| {
+                        Bar::new(month as f64 + offset, *cost as f64).name(format!(
+                            "{} {}\n${:.2}",
+                            month_name(month),
+                            year,
+                            cost
+                        ))
+                    })
+                    .collect();
+                BarChart::new(bars)
+                    .width(width)
+                    .color(colors[idx % colors.len()])
+                    .name(year)
+                    .element_formatter(Box::new(|bar, _chart| bar.name.clone()))
+            })
+            .collect();
+
+        let x_fmt = move |x: f64, _range: &RangeInclusive<f64>| {
+            if x.fract() == 0.0 && (0.0..12.0).contains(&x) {
+                month_name(x as usize)
+            } else {
+                String::new()
+            }
+        };
+        let y_fmt = |y, _range: &RangeInclusive<f64>| format!("${}", y);
+        let mut plot = Plot::new("Comparison Plot")
+            .legend(Legend::default())
+            .include_y(0.0)
+            .x_axis_formatter(x_fmt)
+            .y_axis_formatter(y_fmt)
+            .label_formatter(|_name, val| format!("${:.2}", val.y));
+        if std::mem::take(&mut data_mgr.plot_reset_next_frame) {
+            plot = plot.reset();
+        }
+        plot.show(ui, |plot_ui| {
+            for chart in charts {
+                plot_ui.bar_chart(chart);
+            }
+        });
     }
-}
 
-fn get_width_spacing(group_by: GroupBy) -> (f64, f64) {
-    match group_by {
-        GroupBy::Day => (0.7, 1.0),
-        GroupBy::Month => (21.0, 30.0),
-        GroupBy::Year => (252.0, 360.0),
+    /// A row for each of `rows` with its total in each of `years`, along with how much the newest year changed from
+    /// the one before it
+    fn delta_grid(ui: &mut Ui, id: &str, years: &[i32], rows: Vec<(String, Vec<f32>)>) {
+        Grid::new(id).striped(true).show(ui, |ui| {
+            ui.label("");
+            for year in years {
+                ui.label(RichText::new(year.to_string()).strong());
+            }
+            ui.label(RichText::new("Change").strong());
+            ui.end_row();
+
+            for (label, totals) in rows {
+                ui.label(label);
+                for total in &totals {
+                    ui.label(format!("${:.2}", total));
+                }
+                match totals[..] {
+                    [.., previous, newest] => {
+                        let delta = newest - previous;
+                        let color = if delta > 0.0 {
+                            Color32::RED
+                        } else {
+                            ui.visuals().text_color()
+                        };
+                        let percent = change(previous, newest)
+                            .map_or(String::new(), |c| format!(" ({:+.1}%)", c * 100.0));
+                        ui.label(RichText::new(format!("{:+.2}{}", delta, percent)).color(color));
+                    }
+                    _ => {
+                        ui.label("-");
+                    }
+                }
+                ui.end_row();
+            }
+        });
     }
-}
 
-impl Graph {
-    pub fn ui(&mut self, ui: &mut Ui, data_mgr: &mut DataManager) -> Response {
-        let chart = self.build_chart(data_mgr);
-        self.plot(ui, chart, &mut data_mgr.plot_reset_next_frame)
+    /// `filter` narrowed down to the categories being graphed, which is what the pace chart forecasts
+    pub fn pace_query(&self, filter: &Query) -> Query {
+        filter
+            .clone()
+            .categories(self.settings.selected_categories())
     }
 
-    fn build_chart(&self, backend: &DataManager) -> Vec<BarChart> {
-        let map = backend.cost_map(
-            self.settings.group_by(),
-            self.settings.selected_categories(),
-        );
+    /// `filter` narrowed down to the categories and dates being graphed
+    fn query(&self, filter: &Query) -> Query {
+        let query = filter
+            .clone()
+            .categories(self.settings.selected_categories());
+        match self.settings.date_bounds() {
+            Some((from, to)) => query.within(from, to),
+            None => query,
+        }
+    }
+
+    /// Build a stacked bar chart per category. If limits are shown, each category's limit is drawn as a dashed line
+    /// over its part of every bar (bars that exceed it are outlined), and the overall cap over the whole stack
+    fn build_chart(
+        &self,
+        backend: &DataManager,
+        limits: &Limits,
+        pay_schedule: &PaySchedule,
+        query: &Query,
+    ) -> Chart {
+        let group_by = self.settings.group_by(pay_schedule);
+        let map = backend.cost_map(CostMapView::Chart, group_by.clone(), query);
 
         // TODO: calculate this based on width as well since a wide bar will pass over the line x = 0
         // used to track spacing between bars
         let counter = self.settings.spacing() / 2.0;
 
-        let colors = self.settings.theme().colors();
-        let mut colors_idx = 0;
+        let limit_for = |category: Category| {
+            self.settings
+                .show_limits
+                .then(|| limits.limit(category))
+                .flatten()
+        };
+        // the height of the stack under each bar, so limit lines can be drawn relative to their own category
+        let mut stack_heights: Vec<f64> = Vec::new();
+        let mut stacks: Vec<Vec<(Category, f64)>> = Vec::new();
+        let mut limit_lines: Vec<Line> = Vec::new();
 
         let mut bar_charts: Vec<BarChart> = Vec::new();
-        for (category, inner_map) in &map {
+        for (category, inner_map) in map.iter() {
             // every category except the 'All' category (which shouldn't be in the data anyway) gets graphed
             if *category != Category::All {
+                let limit = limit_for(*category);
+                let color = self.settings.category_color(*category);
+                stack_heights.resize(inner_map.len(), 0.0);
+                stacks.resize(inner_map.len(), Vec::new());
+                // where the limit is over each bar
+                let mut limit_heights: Vec<(f64, f64)> = Vec::new();
+
                 let bars: Vec<_> = inner_map
                     .iter()
                     .enumerate()
                     .map(|(idx, (date, cost))| {
-                        Bar::new(counter + idx as f64 * self.settings.spacing(), *cost as f64)
-                            .fill(colors[colors_idx])
-                            .name(format!("{}: {}", date, category))
+                        let x = counter + idx as f64 * self.settings.spacing();
+                        let bar = Bar::new(x, *cost as f64).fill(color);
+                        let name = format!("{}\n{}\n${:.2}", category, group_by.label(*date), cost);
+                        let base = stack_heights[idx];
+                        stack_heights[idx] += *cost as f64;
+                        if *cost > 0.0 {
+                            stacks[idx].push((*category, stack_heights[idx]));
+                        }
+
+                        let Some(limit) = &limit else {
+                            return bar.name(name);
+                        };
+                        let (start, end) = group_by.range(*date);
+                        let amount = limit.prorated(start, end, pay_schedule);
+                        limit_heights.push((x, base + amount as f64));
+
+                        if *cost > amount {
+                            bar.stroke(Stroke::new(2.0, Color32::RED)).name(format!(
+                                "{}\n${:.2} over the ${:.2} limit",
+                                name,
+                                cost - amount,
+                                amount
+                            ))
+                        } else {
+                            bar.name(format!(
+                                "{}\n${:.2} left of the ${:.2} limit",
+                                name,
+                                amount - cost,
+                                amount
+                            ))
+                        }
                     })
                     .collect();
 
                 let refs: Vec<&BarChart> = bar_charts.iter().collect();
                 let chart = BarChart::new(bars)
                     .width(self.settings.width())
-                    .color(colors[colors_idx])
+                    .color(color)
                     .name(category.to_string())
+                    .element_formatter(Box::new(|bar, _chart| bar.name.clone()))
                     .stack_on(&refs[..]);
 
                 bar_charts.push(chart);
+                if !limit_heights.is_empty() {
+                    limit_lines.push(self.limit_line(
+                        &limit_heights,
+                        color,
+                        format!("{} Limit", category),
+                    ));
+                }
+            }
+        }
+
+        // the overall cap applies to the whole stack
+        if let (Some(cap), Some((_, inner_map))) = (limit_for(Category::All), map.iter().next()) {
+            let heights: Vec<(f64, f64)> = inner_map
+                .keys()
+                .enumerate()
+                .map(|(idx, date)| {
+                    let (start, end) = group_by.range(*date);
+                    (
+                        counter + idx as f64 * self.settings.spacing(),
+                        cap.prorated(start, end, pay_schedule) as f64,
+                    )
+                })
+                .collect();
+            limit_lines.push(self.limit_line(&heights, Color32::RED, "Overall Cap".to_string()));
+        }
+
+        let dates: Vec<NaiveDate> = map
+            .values()
+            .next()
+            .map(|inner_map| inner_map.keys().copied().collect())
+            .unwrap_or_default();
+        let x_axis = XAxis {
+            labels: dates.iter().map(|date| group_by.label(*date)).collect(),
+            dates,
+            offset: counter,
+            spacing: self.settings.spacing(),
+        };
+        Chart {
+            bars: bar_charts,
+            limit_lines,
+            x_axis,
+            stacks,
+        }
+    }
+
+    /// Cumulative spending through each day of the month the graphed dates end in, next to the month before and the
+    /// average of the months before that. If limits are shown, the month's limit is drawn as the target. While the
+    /// month is still going, where it's projected to end up is drawn past today, with the range it's likely to land in
+    fn build_pace(
+        &self,
+        backend: &DataManager,
+        limits: &Limits,
+        pay_schedule: &PaySchedule,
+        filter: &Query,
+    ) -> Pace {
+        let today = chrono::Local::now().date_naive();
+        let last_day = self
+            .settings
+            .date_bounds()
+            .map_or(today, |(_, to)| to.min(today));
+        let month = GroupBy::Month.start_of(last_day);
+        let query = self.pace_query(filter);
+        let points = |totals: &[f32]| -> PlotPoints {
+            totals
+                .iter()
+                .enumerate()
+                .map(|(day, total)| [day as f64 + 1.0, *total as f64])
+                .collect()
+        };
 
-                // use a different color per category
-                colors_idx += 1;
+        // this month, the one before, and the ones averaged over, skipping any from before the first entry
+        let previous_month = month - Months::new(1);
+        let first = backend
+            .first_date(&query)
+            .map(|first| GroupBy::Month.start_of(first));
+        let earlier: Vec<NaiveDate> = (1..=self.settings.pace_months)
+            .map(|n| month - Months::new(n))
+            .filter(|earlier| first.map_or(false, |first| *earlier >= first))
+            .collect();
+        let mut months = vec![month, previous_month];
+        months.extend(&earlier);
+        let mut totals = backend.cumulative_by_day(&query, &months).into_iter();
+        let (mut current, previous) = (totals.next().unwrap(), totals.next().unwrap());
+        let history: Vec<Vec<f32>> = totals.collect();
+
+        let mut lines = Vec::new();
+
+        // the current month hasn't been spent past today yet
+        current.truncate(last_day.day() as usize);
+        lines.push(
+            Line::new(points(&current))
+                .width(2.5)
+                .name(GroupBy::Month.label(month)),
+        );
+
+        let mut band = None;
+        let forecast = backend
+            .forecast(&query, Category::All, today)
+            .filter(|forecast| forecast.start == month && today < forecast.end);
+        if let Some(forecast) = forecast {
+            let (today, end) = (forecast.today.day() as f64, forecast.end.day() as f64);
+            let spent = forecast.spent as f64;
+            lines.push(
+                Line::new(vec![[today, spent], [end, forecast.projected as f64]])
+                    .width(2.0)
+                    .style(LineStyle::dashed_dense())
+                    .name("Forecast"),
+            );
+            band = Some(
+                Polygon::new(vec![
+                    [today, spent],
+                    [end, forecast.high as f64],
+                    [end, forecast.low as f64],
+                ])
+                .fill_alpha(0.15)
+                .name("Forecast"),
+            );
+        }
+
+        lines.push(
+            Line::new(points(&previous))
+                .style(LineStyle::dashed_loose())
+                .name(GroupBy::Month.label(previous_month)),
+        );
+
+        // average every day over the months before this one. A short month has spent its total by the days it
+        // doesn't have
+        if let Some(days) = history.iter().map(Vec::len).max() {
+            let average: Vec<f32> = (0..days)
+                .map(|day| {
+                    let total: f32 = history
+                        .iter()
+                        .filter_map(|totals| totals.get(day).or(totals.last()))
+                        .sum();
+                    total / history.len() as f32
+                })
+                .collect();
+            lines.push(
+                Line::new(points(&average))
+                    .style(LineStyle::dotted_dense())
+                    .name(format!("{} Month Average", history.len())),
+            );
+        }
+
+        // the overall cap if there is one, otherwise what the graphed categories' limits add up to
+        let (start, end) = GroupBy::Month.range(month);
+        let target = match limits.limit(Category::All) {
+            Some(cap) => cap.prorated(start, end, pay_schedule),
+            None => self
+                .settings
+                .selected_categories()
+                .into_iter()
+                .filter(|&category| category != Category::All)
+                .filter_map(|category| limits.limit(category))
+                .map(|limit| limit.prorated(start, end, pay_schedule))
+                .sum(),
+        };
+        if self.settings.show_limits && target > 0.0 {
+            lines.push(
+                Line::new(vec![
+                    [1.0, target as f64],
+                    [end.day() as f64, target as f64],
+                ])
+                .color(Color32::RED)
+                .style(LineStyle::dashed_dense())
+                .width(2.0)
+                .name("Monthly Limit"),
+            );
+        }
+
+        Pace { lines, band }
+    }
+
+    /// Plot cumulative spending lines by day of the month
+    fn plot_pace(&self, ui: &mut Ui, pace: Pace, data_loaded: &mut bool) {
+        // only label whole days
+        let x_fmt = |x: f64, _range: &RangeInclusive<f64>| {
+            if x >= 1.0 && x.fract() == 0.0 {
+                format!("Day {}", x)
+            } else {
+                String::new()
             }
+        };
+        let y_fmt = |y, _range: &RangeInclusive<f64>| format!("${}", y);
+        let label_fmt =
+            |name: &str, val: &PlotPoint| format!("{}\nDay {:.0}\n${:.2}", name, val.x, val.y);
+
+        let mut plot = Plot::new("Pace Plot")
+            .legend(Legend::default())
+            .include_x(1.0)
+            .include_y(0.0)
+            .x_axis_formatter(x_fmt)
+            .y_axis_formatter(y_fmt)
+            .label_formatter(label_fmt);
+        if std::mem::take(data_loaded) {
+            plot = plot.reset();
         }
 
-        bar_charts
+        plot.show(ui, |plot_ui| {
+            if let Some(band) = pace.band {
+                plot_ui.polygon(band);
+            }
+            for line in pace.lines {
+                plot_ui.line(line);
+            }
+        });
+    }
+
+    /// A dashed line across the top of each bar at the given `(x, y)` of its center, joined across the gaps
+    /// between them
+    fn limit_line(&self, heights: &[(f64, f64)], color: Color32, name: String) -> Line {
+        let half_width = self.settings.width() / 2.0;
+        let points: Vec<[f64; 2]> = heights
+            .iter()
+            .flat_map(|&(x, y)| [[x - half_width, y], [x + half_width, y]])
+            .collect();
+        Line::new(points)
+            .color(color)
+            .style(LineStyle::dashed_dense())
+            .width(2.0)
+            .name(name)
     }
 
-    /// Plot various bar charts on a ui
-    fn plot(&self, ui: &mut Ui, charts: Vec<BarChart>, data_loaded: &mut bool) -> Response {
-        // no x labels until (if) I can get custom labels working
-        let x_fmt = |_x, _range: &RangeInclusive<f64>| String::new();
+    /// Plot a chart on a ui. Returns the category and date of the bar segment that was clicked, if any
+    fn plot(
+        &self,
+        ui: &mut Ui,
+        chart: Chart,
+        data_loaded: &mut bool,
+    ) -> Option<(Category, NaiveDate)> {
+        // the formatters have to own what they use, so they share the axis
+        let x_axis = Rc::new(chart.x_axis);
+        let click_axis = Rc::clone(&x_axis);
+
+        // bars are labeled with the period they cover, so only put ticks on bars
+        let spacer_axis = Rc::clone(&x_axis);
+        let x_spacer = move |input: GridInput| spacer_axis.ticks(input);
+        let fmt_axis = Rc::clone(&x_axis);
+        let x_fmt = move |x, _range: &RangeInclusive<f64>| fmt_axis.tick_label(x);
 
-        // since we've removed x axis labels for now, just use the y value ($)
         let y_fmt = |y, _range: &RangeInclusive<f64>| format!("${}", y);
 
-        // formatter used for the cursor label when floating on the graph
-        let label_fmt = |_s: &str, val: &PlotPoint| format!("${:.2}", val.x);
+        // formatter used for the cursor label when floating on the graph (e.g. over a limit line)
+        let label_fmt = move |name: &str, val: &PlotPoint| {
+            let period = x_axis.label_at(val.x).unwrap_or_default();
+            if name.is_empty() {
+                format!("{}\n${:.2}", period, val.y)
+            } else {
+                format!("{}\n{}\n${:.2}", name, period, val.y)
+            }
+        };
 
         // Construct the base plot
         let mut plot = Plot::new("Bar Plot")
             .legend(Legend::default())
             .data_aspect(self.settings.data_aspect())
+            .x_grid_spacer(x_spacer)
             .x_axis_formatter(x_fmt)
             .y_axis_formatter(y_fmt)
             .label_formatter(label_fmt);
@@ -123,31 +661,190 @@ impl Graph {
         }
 
         // Show the plot
-        plot.show(ui, |plot_ui| {
-            for chart in charts {
-                plot_ui.bar_chart(chart);
-            }
-        })
-        .response
+        let clicked = plot
+            .show(ui, |plot_ui| {
+                for bars in chart.bars {
+                    plot_ui.bar_chart(bars);
+                }
+                for line in chart.limit_lines {
+                    plot_ui.line(line);
+                }
+                plot_ui
+                    .plot_clicked()
+                    .then(|| plot_ui.pointer_coordinate())
+                    .flatten()
+            })
+            .inner?;
+
+        // find the segment under the click. Segments are stacked from the bottom up
+        let idx = click_axis.index_at(clicked.x, self.settings.width() / 2.0)?;
+        let date = click_axis.dates[idx];
+        chart
+            .stacks
+            .get(idx)?
+            .iter()
+            .find(|(_, top)| clicked.y >= 0.0 && clicked.y <= *top)
+            .map(|(category, _)| (*category, date))
+    }
+}
+
+/// The total spent in each month, over every category in `map`. `map` has to be grouped by month
+fn month_totals(map: &CostMap) -> [f32; 12] {
+    let mut totals = [0.0; 12];
+    for (_, dates) in map
+        .iter()
+        .filter(|(&category, _)| category != Category::All)
+    {
+        for (date, cost) in dates {
+            totals[date.month0() as usize] += cost;
+        }
+    }
+    totals
+}
+
+/// Everything drawn on the plot, along with what's needed to map positions on it back to entries
+struct Chart {
+    bars: Vec<BarChart>,
+    limit_lines: Vec<Line>,
+    x_axis: XAxis,
+    // the segments of each bar from the bottom up: their category and the height of their top
+    stacks: Vec<Vec<(Category, f64)>>,
+}
+
+/// Everything drawn on the pace plot
+struct Pace {
+    lines: Vec<Line>,
+    // the range the current month's forecast is likely to land in
+    band: Option<Polygon>,
+}
+
+/// Maps positions along the x axis back to the dates the bars were grouped by
+struct XAxis {
+    // the start of the group each bar covers, in order, and what it's labeled
+    dates: Vec<NaiveDate>,
+    labels: Vec<String>,
+    // x of the first bar, and the distance between bars
+    offset: f64,
+    spacing: f64,
+}
+
+impl XAxis {
+    /// The label of the bar nearest `x`, if there is one
+    fn label_at(&self, x: f64) -> Option<&str> {
+        self.index_at(x, self.spacing / 2.0)
+            .map(|idx| self.labels[idx].as_str())
+    }
+
+    /// The index of the bar no further than `max_distance` from `x`, if there is one
+    fn index_at(&self, x: f64, max_distance: f64) -> Option<usize> {
+        let idx = ((x - self.offset) / self.spacing).round();
+        let center = self.offset + idx * self.spacing;
+        if idx < 0.0 || (x - center).abs() > max_distance {
+            return None;
+        }
+        let idx = idx as usize;
+        (idx < self.dates.len()).then_some(idx)
+    }
+
+    /// Label the tick at `x`. Only bar centers are labeled
+    fn tick_label(&self, x: f64) -> String {
+        let idx = (x - self.offset) / self.spacing;
+        if (idx - idx.round()).abs() > 1e-6 {
+            return String::new();
+        }
+        self.label_at(x).unwrap_or_default().to_string()
+    }
+
+    /// A tick on every bar, or on every other (every fourth, ...) bar when they're too close together to label
+    fn ticks(&self, input: GridInput) -> Vec<GridMark> {
+        // base_step_size is about 6 points wide on screen
+        let min_step = input.base_step_size * LABEL_SPACING / 6.0;
+        let mut stride = 1;
+        while (stride as f64) * self.spacing < min_step && stride < self.dates.len() {
+            stride *= 2;
+        }
+
+        let (min, max) = input.bounds;
+        (0..self.dates.len())
+            .step_by(stride)
+            .map(|idx| self.offset + idx as f64 * self.spacing)
+            .filter(|x| (min..=max).contains(x))
+            .map(|value| GridMark {
+                value,
+                step_size: stride as f64 * self.spacing,
+            })
+            .collect()
     }
 }
 
 /// Store settings related to `Graph`. Can draw an egui UI that edits itself.
 /// Only editable via the UI.
-#[derive(Clone)]
+#[derive(serde::Deserialize, serde::Serialize, Clone)]
+#[serde(default)] // if we add new fields, give them default values when deserializing old state
 pub struct GraphSettings {
     width: f64,
     spacing: f64,
     data_aspect: f32,
 
     theme: Theme,
-    group_by: GroupBy,
+    group_by: Grouping,
+    // which day weeks start on, and which month fiscal years start in, when grouping by them
+    week_start: Weekday,
+    fiscal_start: u32,
     category_selector: CategorySelector,
+
+    // draw spending limits over the bars
+    show_limits: bool,
+
+    // bars or monthly pace lines, and how many months the pace is averaged over
+    mode: ChartMode,
+    pace_months: u32,
+    // the category shown in the calendar. All shows every graphed category
+    calendar_category: Category,
+    // the years compared side by side. There are always at least two
+    compare_years: Vec<i32>,
+
+    // which dates are graphed. The custom dates are only used for DateRange::Custom
+    date_range: DateRange,
+    custom_from: NaiveDate,
+    custom_to: NaiveDate,
+    // set when the dates change so the plot can be reset to show them
+    #[serde(skip)]
+    dates_changed: bool,
+}
+
+impl Default for GraphSettings {
+    fn default() -> Self {
+        let group_by = Grouping::Month;
+
+        let (width, spacing) = get_width_spacing(group_by);
+        let today = chrono::Local::now().date_naive();
+
+        Self {
+            width,
+            spacing,
+            data_aspect: DATA_ASPECT,
+            theme: Theme::Sunset,
+            group_by,
+            week_start: Weekday::Mon,
+            fiscal_start: 1,
+            category_selector: CategorySelector::new(),
+            show_limits: true,
+            mode: ChartMode::Bars,
+            pace_months: 3,
+            calendar_category: Category::All,
+            compare_years: vec![today.year() - 1, today.year()],
+            date_range: DateRange::All,
+            custom_from: GroupBy::Year.start_of(today),
+            custom_to: today,
+            dates_changed: false,
+        }
+    }
 }
 
 impl GraphSettings {
     // TODO: is it OK for this not to return a response?
-    pub fn ui(&mut self, ui: &mut Ui) {
+    pub fn ui(&mut self, ui: &mut Ui, pay_schedule: &PaySchedule) {
         Grid::new("grid")
             .num_columns(2)
             .spacing([40.0, 4.0])
@@ -163,19 +860,66 @@ impl GraphSettings {
                     });
                 ui.end_row();
 
+                ui.label("Chart:");
+                ui.horizontal(|ui| {
+                    egui::ComboBox::from_id_source("chart-mode")
+                        .selected_text(self.mode.to_string())
+                        .show_ui(ui, |ui| {
+                            for mode in ChartMode::iter() {
+                                ui.selectable_value(&mut self.mode, mode, mode.to_string());
+                            }
+                        });
+                    if self.mode == ChartMode::Pace {
+                        ui.label("Average over");
+                        ui.add(
+                            egui::DragValue::new(&mut self.pace_months)
+                                .clamp_range(1..=24)
+                                .suffix(" months"),
+                        );
+                    }
+                    if self.mode == ChartMode::Comparison {
+                        self.compare_years_ui(ui);
+                    }
+                });
+                ui.end_row();
+
                 ui.label("Group by:");
-                egui::ComboBox::from_id_source("group")
-                    .selected_text(format!("{}", self.group_by))
-                    .show_ui(ui, |ui| {
-                        let prev_group = self.group_by;
-                        for group in GroupBy::iter() {
-                            ui.selectable_value(&mut self.group_by, group, group.to_string());
-                        }
-                        if prev_group != self.group_by {
-                            // it changed, update our width/spacing to compensate
-                            self.reset_bar_sizing();
-                        }
-                    });
+                ui.horizontal(|ui| self.group_by_ui(ui, pay_schedule));
+                ui.end_row();
+
+                ui.label("Dates:");
+                let prev_dates = (self.date_range, self.custom_from, self.custom_to);
+                ui.vertical(|ui| {
+                    egui::ComboBox::from_id_source("date-range")
+                        .selected_text(self.date_range.to_string())
+                        .show_ui(ui, |ui| {
+                            for range in DateRange::iter() {
+                                ui.selectable_value(&mut self.date_range, range, range.to_string());
+                            }
+                        });
+                    if self.date_range == DateRange::Custom {
+                        ui.horizontal(|ui| {
+                            ui.add(
+                                egui_extras::DatePickerButton::new(&mut self.custom_from)
+                                    .id_source("graph-from"),
+                            );
+                            ui.label("to");
+                            ui.add(
+                                egui_extras::DatePickerButton::new(&mut self.custom_to)
+                                    .id_source("graph-to"),
+                            );
+                        });
+                    }
+                });
+                if prev_dates != (self.date_range, self.custom_from, self.custom_to) {
+                    self.dates_changed = true;
+                }
+                ui.end_row();
+
+                ui.label("Spending limits:");
+                ui.checkbox(&mut self.show_limits, "Show").on_hover_text(
+                    "Draw each category's limit over its bars, and outline bars that exceed it",
+                );
                 ui.end_row();
             });
         ui.group(|ui| {
@@ -206,16 +950,133 @@ impl GraphSettings {
     }
 }
 
+impl GraphSettings {
+    /// Pick the grouping, along with the day weeks start on or the month fiscal years start in
+    fn group_by_ui(&mut self, ui: &mut Ui, pay_schedule: &PaySchedule) {
+        let prev_group = self.group_by;
+        egui::ComboBox::from_id_source("group")
+            .selected_text(format!("{}", self.group_by))
+            .show_ui(ui, |ui| {
+                for group in Grouping::all(self.week_start, self.fiscal_start) {
+                    let label = group.to_string();
+                    ui.selectable_value(&mut self.group_by, group, label);
+                }
+            });
+
+        match self.group_by {
+            Grouping::Week(_) => {
+                ui.label("starting");
+                egui::ComboBox::from_id_source("week-start")
+                    .selected_text(format!("{:?}", self.week_start))
+                    .show_ui(ui, |ui| {
+                        for day in std::iter::successors(Some(Weekday::Mon), |day| Some(day.succ()))
+                            .take(7)
+                        {
+                            ui.selectable_value(&mut self.week_start, day, format!("{:?}", day));
+                        }
+                    });
+                self.group_by = Grouping::Week(self.week_start);
+            }
+            Grouping::FiscalYear(_) => {
+                ui.label("starting in");
+                let month_name = |month| {
+                    NaiveDate::from_ymd_opt(2000, month, 1)
+                        .unwrap()
+                        .format("%B")
+                        .to_string()
+                };
+                egui::ComboBox::from_id_source("fiscal-start")
+                    .selected_text(month_name(self.fiscal_start))
+                    .show_ui(ui, |ui| {
+                        for month in 1..=12 {
+                            ui.selectable_value(&mut self.fiscal_start, month, month_name(month));
+                        }
+                    });
+                self.group_by = Grouping::FiscalYear(self.fiscal_start);
+            }
+            Grouping::PayPeriod => {
+                ui.label(format!("({})", pay_schedule))
+                    .on_hover_text("Change the paydays under Settings -> Pay Schedule");
+            }
+            _ => {}
+        }
+
+        if prev_group != self.group_by {
+            // it changed, update our width/spacing to compensate
+            self.reset_bar_sizing();
+        }
+    }
+
+    /// Edit the years compared side by side. One can be removed as long as two are left. A year dragged onto one
+    /// that's already compared skips past it, so no year is compared with itself
+    fn compare_years_ui(&mut self, ui: &mut Ui) {
+        ui.label("Years:");
+        let mut to_remove = None;
+        let removable = self.compare_years.len() > 2;
+        for index in 0..self.compare_years.len() {
+            let before = self.compare_years[index];
+            ui.add(egui::DragValue::new(&mut self.compare_years[index]).clamp_range(1900..=2100));
+            let step = if self.compare_years[index] < before {
+                -1
+            } else {
+                1
+            };
+            let taken = |year: i32| {
+                self.compare_years
+                    .iter()
+                    .enumerate()
+                    .any(|(other, &compared)| other != index && compared == year)
+            };
+            let mut year = self.compare_years[index];
+            while taken(year) {
+                year += step;
+            }
+            // there's nothing free past the end of the range, so stay put
+            self.compare_years[index] = if (1900..=2100).contains(&year) {
+                year
+            } else {
+                before
+            };
+            if removable && ui.small_button("x").clicked() {
+                to_remove = Some(index);
+            }
+        }
+        if let Some(index) = to_remove {
+            self.compare_years.remove(index);
+        }
+        if ui.small_button("+").clicked() {
+            let earliest = self.compare_years.iter().min().copied();
+            let today = chrono::Local::now().date_naive();
+            self.compare_years
+                .push(earliest.map_or(today.year(), |year| year - 1));
+        }
+    }
+}
+
 /// Getters
 impl GraphSettings {
-    /// The only reason this returns a reference is so that Graph can call get() on it without having to store it in its
-    /// own variable (it's dropped otherwise)
-    fn theme(&self) -> &Theme {
-        &self.theme
+    /// The color `category` is drawn in by the current theme. Every category gets its own, whether it's
+    /// selected or not, so it's the same in every chart
+    pub fn category_color(&self, category: Category) -> Color32 {
+        let colors = self.theme.colors();
+        let idx = Category::iter()
+            .position(|c| c == category)
+            .unwrap_or_default();
+        colors[idx % colors.len()]
+    }
+
+    pub fn is_selected(&self, category: Category) -> bool {
+        self.category_selector.is_selected(category)
     }
 
-    fn group_by(&self) -> GroupBy {
-        self.group_by
+    /// Graph `category` if it isn't already, or stop graphing it if it is
+    pub fn toggle_category(&mut self, category: Category) {
+        self.category_selector.toggle(category);
+    }
+
+    /// What dates are grouped by, with pay periods following `pay_schedule`
+    pub fn group_by<'a>(&self, pay_schedule: &'a PaySchedule) -> GroupBy<'a> {
+        self.group_by.group_by(pay_schedule)
     }
 
     fn width(&self) -> f64 {
@@ -231,6 +1092,21 @@ impl GraphSettings {
         self.category_selector.selected_categories()
     }
 
+    /// The years to compare, oldest first and without repeats
+    fn compare_years(&self) -> Vec<i32> {
+        let mut years = self.compare_years.clone();
+        years.sort_unstable();
+        years.dedup();
+        years
+    }
+
+    /// The first and last day to graph. None to graph everything
+    fn date_bounds(&self) -> Option<(NaiveDate, NaiveDate)> {
+        let today = chrono::Local::now().date_naive();
+        self.date_range
+            .bounds(today, (self.custom_from, self.custom_to))
+    }
+
     fn reset_bar_sizing(&mut self) {
         (self.width, self.spacing) = get_width_spacing(self.group_by);
         self.data_aspect = DATA_ASPECT;
@@ -238,7 +1114,7 @@ impl GraphSettings {
 }
 
 /// Track what `Category`s we'd like to graph
-#[derive(Default, Clone)]
+#[derive(serde::Deserialize, serde::Serialize, Default, Clone)]
 struct CategorySelector {
     selections: HashMap<Category, bool>,
 }
@@ -249,6 +1125,15 @@ impl CategorySelector {
         CategorySelector { selections }
     }
 
+    fn is_selected(&self, category: Category) -> bool {
+        self.selections.get(&category).copied().unwrap_or_default()
+    }
+
+    fn toggle(&mut self, category: Category) {
+        let selected = self.selections.entry(category).or_insert(false);
+        *selected = !*selected;
+    }
+
     fn selected_categories(&self) -> Vec<Category> {
         self.selections
             .iter()
@@ -293,3 +1178,58 @@ impl CategorySelector {
             });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_month_totals() {
+        let date = |year, month| NaiveDate::from_ymd_opt(year, month, 1).unwrap();
+        let mut map = CostMap::new();
+        map.insert(
+            Category::Groceries,
+            BTreeMap::from([(date(2023, 1), 10.0), (date(2023, 3), 5.0)]),
+        );
+        map.insert(Category::Car, BTreeMap::from([(date(2023, 3), 20.0)]));
+        // the total over every category is already counted by the others
+        map.insert(Category::All, BTreeMap::from([(date(2023, 3), 25.0)]));
+
+        let totals = month_totals(&map);
+        assert_eq!(totals[..3], [10.0, 0.0, 25.0]);
+        assert_eq!(totals.iter().sum::<f32>(), 35.0);
+    }
+
+    #[test]
+    fn test_x_axis() {
+        let date = |month| NaiveDate::from_ymd_opt(2024, month, 1).unwrap();
+        let dates: Vec<NaiveDate> = (1..=6).map(date).collect();
+        let x_axis = XAxis {
+            labels: dates
+                .iter()
+                .map(|date| GroupBy::Month.label(*date))
+                .collect(),
+            dates,
+            offset: 15.0,
+            spacing: 30.0,
+        };
+        assert_eq!(x_axis.label_at(75.0), Some("Mar 2024"));
+        assert_eq!(x_axis.label_at(84.0), Some("Mar 2024"));
+        assert_eq!(x_axis.label_at(-20.0), None);
+        assert_eq!(x_axis.label_at(200.0), None);
+        assert_eq!(x_axis.tick_label(75.0), "Mar 2024");
+        assert_eq!(x_axis.tick_label(80.0), "");
+
+        // clicks only land on a bar within its width
+        assert_eq!(x_axis.index_at(84.0, 10.5), Some(2));
+        assert_eq!(x_axis.index_at(88.0, 10.5), None);
+
+        // zoomed out so only every other bar has room for a label
+        let ticks = x_axis.ticks(GridInput {
+            bounds: (0.0, 180.0),
+            base_step_size: 3.0,
+        });
+        let values: Vec<f64> = ticks.iter().map(|mark| mark.value).collect();
+        assert_eq!(values, [15.0, 75.0, 135.0]);
+    }
+}
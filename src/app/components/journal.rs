@@ -0,0 +1,150 @@
+use crate::backend::DataManager;
+use crate::journal::*;
+use egui::Ui;
+use strum::IntoEnumIterator;
+
+#[cfg(target_arch = "wasm32")]
+use crate::app::FileResponse;
+#[cfg(target_arch = "wasm32")]
+use std::sync::{Arc, Mutex};
+
+/// Import and export entries as a plain-text accounting journal (ledger/hledger or beancount)
+#[derive(serde::Deserialize, serde::Serialize, Clone)]
+#[serde(default)] // if we add new fields, give them default values when deserializing old state
+pub struct Journal {
+    format: JournalFormat,
+    // every exported expense is paid from this account
+    funding_account: String,
+}
+
+impl Default for Journal {
+    fn default() -> Self {
+        Self {
+            format: JournalFormat::Ledger,
+            funding_account: "Assets:Checking".to_string(),
+        }
+    }
+}
+
+impl Journal {
+    pub fn ui(
+        &mut self,
+        ui: &mut Ui,
+        data_mgr: &mut DataManager,
+        #[cfg(target_arch = "wasm32")] file_pick: &Arc<Mutex<Option<FileResponse>>>,
+    ) {
+        egui::Grid::new("journal-grid")
+            .num_columns(2)
+            .striped(true)
+            .show(ui, |ui| {
+                ui.label("Format:");
+                egui::ComboBox::from_id_source("journal-format")
+                    .selected_text(self.format.to_string())
+                    .show_ui(ui, |ui| {
+                        for format in JournalFormat::iter() {
+                            ui.selectable_value(&mut self.format, format, format.to_string());
+                        }
+                    });
+                ui.end_row();
+
+                ui.label("Funding account:").on_hover_text(
+                    "Exported expenses are paid from this account. It's ignored on import, where every posting to an Expenses account becomes an entry",
+                );
+                ui.text_edit_singleline(&mut self.funding_account);
+                ui.end_row();
+            });
+
+        ui.horizontal(|ui| {
+            if ui.button("Import").clicked() {
+                #[cfg(not(target_arch = "wasm32"))]
+                self.import(data_mgr);
+                #[cfg(target_arch = "wasm32")]
+                self.import(file_pick);
+            }
+            let export_enabled = !self.funding_account.trim().is_empty();
+            if ui
+                .add_enabled(export_enabled, egui::Button::new("Export"))
+                .clicked()
+            {
+                self.export(data_mgr);
+            }
+        });
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn import(&self, data_mgr: &mut DataManager) {
+        let Some(file_path) = rfd::FileDialog::new()
+            .add_filter(
+                self.format.to_string(),
+                &[self.format.extension(), "ledger"],
+            )
+            .pick_file()
+        else {
+            return;
+        };
+
+        let result = std::fs::read_to_string(&file_path)
+            .map_err(Box::<dyn std::error::Error>::from)
+            .and_then(|text| read_journal(&text, self.format));
+        match result {
+            Ok(entries) => data_mgr.load_entries(entries),
+            Err(e) => error!("Error importing journal \"{:?}\": {}", file_path, e),
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn export(&self, data_mgr: &DataManager) {
+        let Some(file_path) = rfd::FileDialog::new()
+            .add_filter(self.format.to_string(), &[self.format.extension()])
+            .set_file_name(format!("pennypilot.{}", self.format.extension()))
+            .save_file()
+        else {
+            return;
+        };
+
+        let journal = write_journal(&data_mgr.entries, self.format, self.funding_account.trim());
+        if let Err(e) = std::fs::write(&file_path, journal) {
+            error!("Error exporting journal to \"{:?}\": {}", file_path, e);
+        }
+    }
+
+    /// The picked file is read asynchronously and handed back to the app through `file_pick`
+    #[cfg(target_arch = "wasm32")]
+    fn import(&self, file_pick: &Arc<Mutex<Option<FileResponse>>>) {
+        let file_pick_clone = file_pick.clone();
+        let format = self.format;
+        wasm_bindgen_futures::spawn_local(async move {
+            let file = rfd::AsyncFileDialog::new().pick_file().await;
+            let pick = match file {
+                None => FileResponse::NoFile,
+                Some(handle) => {
+                    let data = handle.read().await;
+                    let result = String::from_utf8(data)
+                        .map_err(Box::<dyn std::error::Error>::from)
+                        .and_then(|text| read_journal(&text, format));
+                    match result {
+                        Ok(entries) => FileResponse::FileData(entries),
+                        Err(e) => FileResponse::Error(e),
+                    }
+                }
+            };
+            // don't hold the lock while the dialog is open, the app polls it every frame
+            *file_pick_clone.lock().unwrap() = Some(pick);
+        });
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn export(&self, data_mgr: &DataManager) {
+        let journal = write_journal(&data_mgr.entries, self.format, self.funding_account.trim());
+        wasm_bindgen_futures::spawn_local(async move {
+            let handle = rfd::AsyncFileDialog::new().save_file().await;
+            match handle {
+                None => error!("export journal: didn't get the save file handle"),
+                Some(handle) => match handle.write(journal.as_bytes()).await {
+                    Ok(_) => debug!("Successfully exported journal!"),
+                    Err(e) => error!("Error while exporting journal: {e}"),
+                },
+            }
+        });
+    }
+}
@@ -1,17 +1,26 @@
+use super::alerts::{Alert, AlertLevel};
 use crate::backend::DataManager;
 use crate::category::Category;
 use crate::entry::Entry;
+use crate::limit::{Limit, LimitPeriod};
+use crate::payperiod::PaySchedule;
+use crate::query::Query;
 use chrono::Datelike;
 use chrono::NaiveDate;
-use egui::Ui;
-use std::collections::HashMap;
+use egui::{Color32, RichText, Ui};
+use std::collections::{BTreeMap, HashMap};
 use strum::IntoEnumIterator;
 
+#[derive(serde::Deserialize, serde::Serialize, Clone)]
+#[serde(default)] // if we add new fields, give them default values when deserializing old state
 pub struct Limits {
     // each category has an optional spending limit associated with it
     // this will be used to warn the user when they're spending too much :)
-    // for now, this is ONLY by month.
-    limits: HashMap<Category, Option<f32>>,
+    // the limit for Category::All is an overall cap across every category
+    limits: HashMap<Category, Option<Limit>>,
+
+    // percentages of a limit that raise an "approaching" alert when spending passes them
+    thresholds: Vec<f32>,
 
     warnings_enabled: bool,
 }
@@ -20,6 +29,7 @@ impl Default for Limits {
     fn default() -> Self {
         Self {
             limits: Category::iter().map(|category| (category, None)).collect(),
+            thresholds: vec![80.0],
             warnings_enabled: true,
         }
     }
@@ -27,38 +37,170 @@ impl Default for Limits {
 
 // display spending limits
 impl Limits {
-    pub fn ui(&mut self, ui: &mut Ui, _backend: &DataManager) {
-        let today = Limits::current_date();
-        let hover_text = format!("When checked, Rudget will warn you if you exceed a category-wise spending limit when adding an entry. Note that warnings only apply to entries added in the current month ({}/{}).", today.month(), today.year());
+    /// Limits that apply per pay period follow `pay_schedule`, and projections only count entries matching `query`
+    pub fn ui(
+        &mut self,
+        ui: &mut Ui,
+        backend: &DataManager,
+        pay_schedule: &PaySchedule,
+        query: &Query,
+    ) {
+        let hover_text = "When checked, PennyPilot will warn you if you approach or exceed a spending limit when adding an entry. Note that warnings only apply to entries added in a limit's current period (this week, month, etc.).";
         ui.checkbox(&mut self.warnings_enabled, "Enable Spending Warnings")
             .on_hover_text(hover_text);
+        self.thresholds_ui(ui);
+        let today = Limits::current_date();
         egui::Grid::new("spending-limits-grid")
-            .num_columns(2)
-            // .spacing([40.0, 4.0])
+            .num_columns(4)
             .striped(true)
             .show(ui, |ui| {
-                for (category, limit) in self.limits.iter_mut() {
-                    ui.horizontal(|ui| {
+                // iterate categories rather than the map so rows stay in the same order
+                for category in Category::iter() {
+                    let limit = self.limits.entry(category).or_default();
+                    if category == Category::All {
+                        ui.label("All (overall cap)");
+                    } else {
                         ui.label(category.to_string());
-                        let mut display_value = limit.unwrap_or(0.0);
-                        ui.add(
-                            egui::DragValue::new(&mut display_value)
-                                .speed(10.0)
-                                .clamp_range(0.0..=1_000_000.0)
-                                .prefix("$"),
-                        );
-
-                        *limit = if display_value == 0.0 {
-                            None
-                        } else {
-                            Some(display_value)
-                        };
-                    });
+                    }
+
+                    let mut display_value = limit.as_ref().map_or(0.0, |limit| limit.amount);
+                    ui.add(
+                        egui::DragValue::new(&mut display_value)
+                            .speed(10.0)
+                            .clamp_range(0.0..=1_000_000.0)
+                            .prefix("$"),
+                    );
+                    *limit = match (display_value == 0.0, limit.take()) {
+                        (true, _) => None,
+                        (false, Some(limit)) => Some(Limit {
+                            amount: display_value,
+                            ..limit
+                        }),
+                        (false, None) => Some(Limit::monthly(display_value)),
+                    };
+
+                    match limit {
+                        Some(limit) => {
+                            Self::period_ui(ui, category, limit, pay_schedule);
+                            match backend.limit_status(category, limit, today, pay_schedule) {
+                                Some(status) => {
+                                    let color = if status.remaining() < 0.0 {
+                                        Color32::RED
+                                    } else {
+                                        ui.visuals().text_color()
+                                    };
+                                    let projected =
+                                        Self::projected(category, limit, today, backend, query);
+                                    ui.horizontal(|ui| {
+                                        ui.label(
+                                            RichText::new(format!(
+                                                "${:.2} left",
+                                                status.remaining()
+                                            ))
+                                            .color(color),
+                                        )
+                                        .on_hover_text(status.to_string());
+                                        // only warn ahead of time, before the limit's been exceeded
+                                        if let Some(projected) = projected.filter(|&projected| {
+                                            status.remaining() >= 0.0 && projected > limit.amount
+                                        }) {
+                                            ui.label(
+                                                RichText::new(format!(
+                                                    "(on track for ${:.2})",
+                                                    projected
+                                                ))
+                                                .color(AlertLevel::Projected.color()),
+                                            )
+                                            .on_hover_text(
+                                                "Where spending is projected to land by the end of the month",
+                                            );
+                                        }
+                                    });
+                                }
+                                None => {
+                                    ui.label("Not active today");
+                                }
+                            }
+                        }
+                        None => {
+                            ui.label("");
+                            ui.label("");
+                        }
+                    }
                     ui.end_row();
                 }
             });
     }
 
+    /// Choose the period of `category`'s limit, and its dates if it's custom
+    fn period_ui(ui: &mut Ui, category: Category, limit: &mut Limit, pay_schedule: &PaySchedule) {
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_source(("limit-period", category))
+                .selected_text(limit.period.to_string())
+                .show_ui(ui, |ui| {
+                    for period in LimitPeriod::iter() {
+                        ui.selectable_value(&mut limit.period, period, period.to_string());
+                    }
+                });
+            if limit.period == LimitPeriod::PayPeriod {
+                ui.label(format!("({})", pay_schedule))
+                    .on_hover_text("Change the paydays under Settings -> Pay Schedule");
+            }
+            if limit.period == LimitPeriod::Custom {
+                let start_id = format!("limit-start-{:?}", category);
+                let end_id = format!("limit-end-{:?}", category);
+                ui.add(egui_extras::DatePickerButton::new(&mut limit.start).id_source(&start_id));
+                ui.label("to");
+                ui.add(egui_extras::DatePickerButton::new(&mut limit.end).id_source(&end_id));
+            }
+        });
+    }
+
+    /// The limit set for `category`, if any. `Category::All` gets the overall cap
+    pub fn limit(&self, category: Category) -> Option<Limit> {
+        self.limits.get(&category).cloned().flatten()
+    }
+
+    /// Each category's monthly limit is what its envelope gets every month. The overall cap doesn't have one
+    pub fn monthly_allocations(&self) -> BTreeMap<Category, f32> {
+        self.limits
+            .iter()
+            .filter(|(&category, _)| category != Category::All)
+            .filter_map(|(&category, limit)| match limit {
+                Some(limit) if limit.period == LimitPeriod::Monthly => {
+                    Some((category, limit.amount))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Edit the percentages that raise an alert before a limit is reached
+    fn thresholds_ui(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Warn at:")
+                .on_hover_text("Raise an alert when spending passes these percentages of a limit");
+            let mut to_remove = None;
+            for (index, threshold) in self.thresholds.iter_mut().enumerate() {
+                ui.add(
+                    egui::DragValue::new(threshold)
+                        .speed(1.0)
+                        .clamp_range(1.0..=99.0)
+                        .suffix("%"),
+                );
+                if ui.small_button("x").clicked() {
+                    to_remove = Some(index);
+                }
+            }
+            if let Some(index) = to_remove {
+                self.thresholds.remove(index);
+            }
+            if ui.small_button("+").clicked() {
+                self.thresholds.push(90.0);
+            }
+        });
+    }
+
     fn current_date() -> NaiveDate {
         NaiveDate::from_ymd_opt(
             chrono::Local::now().year(),
@@ -68,43 +210,221 @@ impl Limits {
         .unwrap()
     }
 
-    // check whether a limit was exceeded with the addition of 'entry'
-    pub fn check_limit(&self, entry: &Entry, backend: &DataManager) {
+    /// How close `total` is to `limit`, if it's close enough to warn about. If it isn't yet, but `projected` (where
+    /// spending is headed by the end of the period) is over the limit, it's warned about early
+    fn level(&self, total: f32, projected: Option<f32>, limit: f32) -> Option<AlertLevel> {
+        if total > limit {
+            Some(AlertLevel::Exceeded)
+        } else if total >= limit {
+            Some(AlertLevel::Reached)
+        } else {
+            self.thresholds
+                .iter()
+                .filter(|&&percent| total >= limit * percent / 100.0)
+                .max_by(|a, b| a.total_cmp(b))
+                .map(|&percent| AlertLevel::Approaching(percent))
+                .or_else(|| {
+                    projected
+                        .filter(|&projected| projected > limit)
+                        .map(|_| AlertLevel::Projected)
+                })
+        }
+    }
+
+    /// Where spending matching `query` in `category` is projected to land by the end of the month containing
+    /// `date`. Only monthly limits are forecast, so None for the others
+    fn projected(
+        category: Category,
+        limit: &Limit,
+        date: NaiveDate,
+        backend: &DataManager,
+        query: &Query,
+    ) -> Option<f32> {
+        if limit.period != LimitPeriod::Monthly {
+            return None;
+        }
+        backend
+            .forecast(query, category, date)
+            .map(|forecast| forecast.projected)
+    }
+
+    /// Check whether the addition of `entry` (already added to `backend`) pushed its category, or the overall cap,
+    /// past a threshold or limit. Limits that apply per pay period follow `pay_schedule`, and projections only count
+    /// entries matching `query`, the same ones the pace chart forecasts
+    pub fn check_limit(
+        &self,
+        entry: &Entry,
+        backend: &DataManager,
+        pay_schedule: &PaySchedule,
+        query: &Query,
+    ) -> Vec<Alert> {
         if !self.warnings_enabled {
             debug!("Warnings are disabled. Skipping spending limits check");
-            return;
+            return vec![];
         }
-        if let Some(limit) = self.limits[&entry.category] {
-            // get the sum of all items in the category for the month from the backend
-            // what about retroactively adding entries? should we still be warned for those?
-            // should check the relevant date range and only warn for items added in the current month?
-
-            // for now, only going to make this work for entries that are added to the current month.
-            // anything else will be considered retroactive, and spending limits won't generate a warning.
-            // limits can additionally be graphed though so you can see when they're exceeded
-            let today: NaiveDate = Limits::current_date();
-            if today.month() == entry.date.month() && today.year() == entry.date.year() {
-                // get the sum for entry.category for this date
-                let cost = backend.monthly_cost(entry.category, entry.date);
-
-                if cost >= limit {
-                    // limit has been met/exceeded!
-                    // warn the user
-                    warn!(
-                        "Limit for category: {} (${}) has been exceeded!",
-                        entry.category, limit
-                    );
-                } else {
-                    debug!(
-                        "Limit for category: {} (${}) has NOT been exceeded. Total cost is {cost}",
-                        entry.category, limit
-                    );
-                }
-            } else {
-                debug!("Entry's date doesn't match the current month. Skipping limit check");
-            }
-        } else {
-            debug!("No limit set for category: {}", entry.category);
+        [entry.category, Category::All]
+            .into_iter()
+            .filter_map(|category| {
+                self.check_category(category, entry, backend, pay_schedule, query)
+            })
+            .collect()
+    }
+
+    /// Only crossing into a new level raises an alert, so adding more entries after a limit is exceeded doesn't
+    /// raise the same alert again
+    fn check_category(
+        &self,
+        category: Category,
+        entry: &Entry,
+        backend: &DataManager,
+        pay_schedule: &PaySchedule,
+        query: &Query,
+    ) -> Option<Alert> {
+        let Some(limit) = self.limit(category) else {
+            debug!("No limit set for category: {}", category);
+            return None;
+        };
+
+        // only entries added to the limit's current period are checked. Anything else is considered
+        // retroactive, and spending limits won't generate an alert. Limits can additionally be graphed so you
+        // can see when they're exceeded
+        let today: NaiveDate = Limits::current_date();
+        let status = backend.limit_status(category, &limit, entry.date, pay_schedule)?;
+        if today < status.start || status.end < today {
+            debug!("Entry's date isn't in the limit's current period. Skipping limit check");
+            return None;
+        }
+
+        let cost = f32::from(entry.cost);
+        let before = status.spent - cost;
+        let projected = Self::projected(category, &limit, today, backend, query);
+        let level = self.level(status.spent, projected, limit.amount)?;
+        if self.level(
+            before,
+            projected.map(|projected| projected - cost),
+            limit.amount,
+        ) >= Some(level)
+        {
+            debug!(
+                "Limit for category: {} (${}) is still at '{}'. Total cost is {}",
+                category, limit.amount, level, status.spent
+            );
+            return None;
+        }
+
+        Some(Alert {
+            category,
+            status,
+            level,
+            entry: entry.clone(),
+            entries: backend
+                .query(
+                    &Query::default()
+                        .category(category)
+                        .between(status.start, status.end),
+                )
+                .entries()
+                .cloned()
+                .collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entry::Cost;
+
+    #[test]
+    fn test_check_limit() {
+        let mut limits = Limits::default();
+        limits
+            .limits
+            .insert(Category::Groceries, Some(Limit::monthly(100.0)));
+        let mut backend = DataManager::default();
+
+        let mut add = |cost: f32, category: Category| {
+            let entry = Entry {
+                cost: Cost::try_from(cost).unwrap(),
+                date: Limits::current_date(),
+                category,
+                ..Default::default()
+            };
+            backend.entries.insert(entry.clone());
+            limits
+                .check_limit(&entry, &backend, &PaySchedule::default(), &Query::default())
+                .iter()
+                .map(|alert| (alert.category, alert.level))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(add(50.0, Category::Groceries), vec![]);
+        assert_eq!(
+            add(35.0, Category::Groceries),
+            vec![(Category::Groceries, AlertLevel::Approaching(80.0))]
+        );
+        // still past 80%, so nothing new
+        assert_eq!(add(5.0, Category::Groceries), vec![]);
+        assert_eq!(
+            add(10.0, Category::Groceries),
+            vec![(Category::Groceries, AlertLevel::Reached)]
+        );
+        assert_eq!(
+            add(1.0, Category::Groceries),
+            vec![(Category::Groceries, AlertLevel::Exceeded)]
+        );
+        assert_eq!(add(1.0, Category::Groceries), vec![]);
+    }
+
+    #[test]
+    fn test_level() {
+        let limits = Limits::default();
+        assert_eq!(limits.level(50.0, None, 100.0), None);
+        assert_eq!(
+            limits.level(50.0, Some(120.0), 100.0),
+            Some(AlertLevel::Projected)
+        );
+        // being over a threshold is more pressing than the projection
+        assert_eq!(
+            limits.level(85.0, Some(120.0), 100.0),
+            Some(AlertLevel::Approaching(80.0))
+        );
+        assert_eq!(limits.level(50.0, Some(90.0), 100.0), None);
+    }
+
+    #[test]
+    fn test_overall_cap() {
+        let mut limits = Limits::default();
+        let weekly = Limit {
+            period: LimitPeriod::Weekly,
+            ..Limit::monthly(50.0)
+        };
+        limits.limits.insert(Category::All, Some(weekly));
+        let mut backend = DataManager::default();
+
+        let mut alerts = vec![];
+        for category in [Category::Car, Category::Rent, Category::Clothes] {
+            let entry = Entry {
+                cost: Cost::try_from(20.0).unwrap(),
+                date: Limits::current_date(),
+                category,
+                ..Default::default()
+            };
+            backend.entries.insert(entry.clone());
+            alerts.extend(limits.check_limit(
+                &entry,
+                &backend,
+                &PaySchedule::default(),
+                &Query::default(),
+            ));
         }
+
+        let levels: Vec<AlertLevel> = alerts.iter().map(|alert| alert.level).collect();
+        assert_eq!(
+            levels,
+            vec![AlertLevel::Approaching(80.0), AlertLevel::Exceeded]
+        );
+        assert!(alerts.iter().all(|alert| alert.category == Category::All));
+        assert_eq!(alerts[1].entries.len(), 3);
     }
 }
@@ -1,5 +1,7 @@
 use super::super::App;
-use egui::{Color32, RichText, Ui};
+use egui::Ui;
+#[cfg(not(target_arch = "wasm32"))]
+use egui::{Color32, RichText};
 
 pub struct MainPage {}
 
@@ -31,11 +33,29 @@ impl MainPage {
         // show the 'add entry' ui. Check spending limits if something was added
         if let Some(entry) = app.add_entry_view.ui(ui, &mut app.data_mgr) {
             debug!("Entry added! Checking spending limits.");
-            app.spending_limits.check_limit(&entry, &app.data_mgr);
+            let alerts = app.spending_limits.check_limit(
+                &entry,
+                &app.data_mgr,
+                &app.pay_schedule.schedule,
+                &app.graph.pace_query(&app.filter.query),
+            );
+            for alert in alerts {
+                app.alerts.raise(alert, ui.input(|i| i.time));
+            }
         }
 
-        // show the graph ui
-        app.graph.ui(ui, &mut app.data_mgr);
+        // show the graph ui. Clicking a bar lists the entries behind it
+        if let Some(drill_down) = app.graph.ui(
+            ui,
+            &mut app.data_mgr,
+            &app.spending_limits,
+            &app.pay_schedule.schedule,
+            &app.filter.query,
+        ) {
+            debug!("Drilling down into {}", drill_down.title);
+            app.drill_down = Some(drill_down);
+            app.window_state.drill_down_open = true;
+        }
     }
 
     fn links(ui: &mut Ui) {
@@ -54,11 +74,17 @@ impl MainPage {
         });
     }
 
+    #[cfg(not(target_arch = "wasm32"))]
     fn active_file(ui: &mut Ui, app: &mut App) {
         ui.horizontal(|ui| {
             ui.label("Active file: ");
             let (label, color) = if let Some(file) = &app.data_mgr.active_file {
                 (format!("{:?}", file), Color32::DARK_GREEN)
+            } else if let Some(ledger) = &app.data_mgr.active_ledger {
+                (
+                    format!("{:?} (one file per year)", ledger.pattern),
+                    Color32::DARK_GREEN,
+                )
             } else {
                 (
                     "No active file set. Use File -> Import to add one".to_string(),
@@ -67,7 +93,31 @@ impl MainPage {
             };
 
             ui.label(RichText::new(label).color(color));
+            if let Some(format) = app.data_mgr.detected_csv_format {
+                ui.label(format!("({})", format));
+            }
         });
+
+        // older files are left alone until the user asks for them to be rewritten
+        let has_file = app.data_mgr.active_file.is_some() || app.data_mgr.active_ledger.is_some();
+        if has_file && app.data_mgr.csv_outdated() {
+            ui.horizontal(|ui| {
+                ui.label(
+                    RichText::new(format!(
+                        "This file uses an older csv layout (v{})",
+                        app.data_mgr.csv_version
+                    ))
+                    .color(Color32::YELLOW),
+                );
+                if ui
+                    .button("Upgrade file")
+                    .on_hover_text("Rewrite the file with the newest layout")
+                    .clicked()
+                {
+                    app.data_mgr.upgrade_csv();
+                }
+            });
+        }
     }
 }
 
@@ -20,11 +20,41 @@ impl MenuBar {
             ui.menu_button("File", |ui| {
                 Self::import_button(ui, app);
                 Self::export_button(ui, app);
+                Self::bundle_buttons(ui, app);
+
+                if ui.button("Plain-Text Journal").clicked() {
+                    app.window_state.journal_open = true;
+                }
 
                 if ui.button("View Entries").clicked() {
                     app.window_state.entry_open = true;
                 }
 
+                let filter_label = if app.filter.is_active() {
+                    "Filter (Active)"
+                } else {
+                    "Filter"
+                };
+                if ui.button(filter_label).clicked() {
+                    app.window_state.filter_open = true;
+                }
+
+                if ui.button("Envelope Budget").clicked() {
+                    app.window_state.budget_open = true;
+                }
+
+                if ui.button("Category Share").clicked() {
+                    app.window_state.donut_open = true;
+                }
+
+                if ui.button("Statistics").clicked() {
+                    app.window_state.statistics_open = true;
+                }
+
+                if ui.button("Subscriptions").clicked() {
+                    app.window_state.subscriptions_open = true;
+                }
+
                 ui.menu_button("Settings", |ui| {
                     if ui
                         .add_enabled(
@@ -44,8 +74,37 @@ impl MenuBar {
                     {
                         app.window_state.spending_limits_open = true;
                     }
+                    if ui
+                        .add_enabled(
+                            !app.window_state.pay_schedule_open,
+                            egui::Button::new("Pay Schedule"),
+                        )
+                        .clicked()
+                    {
+                        app.window_state.pay_schedule_open = true;
+                    }
+                    if ui
+                        .add_enabled(
+                            !app.window_state.csv_settings_open,
+                            egui::Button::new("CSV Format"),
+                        )
+                        .clicked()
+                    {
+                        app.window_state.csv_settings_open = true;
+                    }
                 });
 
+                if ui
+                    .button(format!("Alerts ({})", app.alerts.count()))
+                    .clicked()
+                {
+                    app.window_state.alerts_open = true;
+                }
+
+                if ui.button("Attention").clicked() {
+                    app.window_state.attention_open = true;
+                }
+
                 #[cfg(not(target_arch = "wasm32"))] // not supported on wasm
                 if ui.button("Quit").clicked() {
                     frame.close();
@@ -65,6 +124,16 @@ impl MenuBar {
                 app.data_mgr.read_entries_from_csv(file_path);
             }
         }
+
+        if ui
+            .button("Import Directory")
+            .on_hover_text("Load a directory with one CSV file per year (2021.csv, 2022.csv, ...) as a single ledger")
+            .clicked()
+        {
+            if let Some(dir_path) = FileDialog::new().pick_folder() {
+                app.data_mgr.read_entries_from_dir(dir_path);
+            }
+        }
     }
 
     // TODO: worth restricting this even further?
@@ -83,6 +152,105 @@ impl MenuBar {
         }
     }
 
+    /// A bundle holds the entries plus every setting, for moving PennyPilot to another machine
+    #[cfg(not(target_arch = "wasm32"))]
+    fn bundle_buttons(ui: &mut Ui, app: &mut App) {
+        use crate::app::bundle::Bundle;
+
+        if ui
+            .button("Import Bundle")
+            .on_hover_text("Replace all entries and settings with the ones in a bundle")
+            .clicked()
+        {
+            if let Some(file_path) = FileDialog::new()
+                .add_filter("PennyPilot Bundle", &["json"])
+                .pick_file()
+            {
+                let result = std::fs::read_to_string(&file_path)
+                    .map_err(Box::<dyn std::error::Error>::from)
+                    .and_then(|json| Bundle::from_json(&json));
+                match result {
+                    Ok(bundle) => app.import_bundle(bundle),
+                    Err(e) => error!("Error importing bundle \"{:?}\": {}", file_path, e),
+                }
+            }
+        }
+
+        if ui
+            .button("Export Bundle")
+            .on_hover_text("Save all entries and settings to a single file")
+            .clicked()
+        {
+            if let Some(file_path) = FileDialog::new()
+                .add_filter("PennyPilot Bundle", &["json"])
+                .set_file_name("pennypilot.json")
+                .save_file()
+            {
+                let result = app
+                    .bundle()
+                    .to_json()
+                    .and_then(|json| Ok(std::fs::write(&file_path, json)?));
+                if let Err(e) = result {
+                    error!("Error exporting bundle to \"{:?}\": {}", file_path, e);
+                }
+            }
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn bundle_buttons(ui: &mut Ui, app: &mut App) {
+        use crate::app::bundle::Bundle;
+
+        if ui
+            .button("Import Bundle")
+            .on_hover_text("Replace all entries and settings with the ones in a bundle")
+            .clicked()
+        {
+            let file_pick_clone = app.file_pick.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                let file = AsyncFileDialog::new().set_directory(".").pick_file().await;
+                let pick = match file {
+                    None => FileResponse::NoFile,
+                    Some(handle) => {
+                        let data = handle.read().await;
+                        let result = String::from_utf8(data)
+                            .map_err(Box::<dyn std::error::Error>::from)
+                            .and_then(|json| Bundle::from_json(&json));
+                        match result {
+                            Ok(bundle) => FileResponse::Bundle(Box::new(bundle)),
+                            Err(e) => FileResponse::Error(e),
+                        }
+                    }
+                };
+                *file_pick_clone.lock().unwrap() = Some(pick);
+            });
+        }
+
+        if ui
+            .button("Export Bundle")
+            .on_hover_text("Save all entries and settings to a single file")
+            .clicked()
+        {
+            let json = match app.bundle().to_json() {
+                Ok(json) => json,
+                Err(e) => {
+                    error!("export bundle: couldn't build the bundle: {e}");
+                    return;
+                }
+            };
+            wasm_bindgen_futures::spawn_local(async move {
+                let handle = AsyncFileDialog::new().set_directory(".").save_file().await;
+                match handle {
+                    None => error!("export bundle: didn't get the save file handle"),
+                    Some(handle) => match handle.write(json.as_bytes()).await {
+                        Ok(_) => debug!("Successfully exported bundle!"),
+                        Err(e) => error!("Error while exporting bundle: {e}"),
+                    },
+                }
+            });
+        }
+    }
+
     #[cfg(target_arch = "wasm32")]
     fn import_button(ui: &mut Ui, app: &mut App) {
         // TODO: add this button as enabled only when the file pick channel is None
@@ -90,6 +258,7 @@ impl MenuBar {
         //      impl a new trait for PathBuf and rfd::FilePath so we can read each from a csv
         if ui.button("Import").clicked() {
             let file_pick_clone = app.file_pick.clone();
+            let csv_format = app.data_mgr.csv_format;
 
             // Spawn a thread that does the work of getting the picked file and reading the contents
             // into a vector. The vector is what we send back to the main thread so it can update its
@@ -105,31 +274,14 @@ impl MenuBar {
                     Some(handle) => {
                         use crate::csvadapter::read_entries_from_vec;
                         let data = handle.read().await;
-                        match read_entries_from_vec(data) {
-                            Ok(entry_vec) => Some(FileResponse::FileData(entry_vec)),
+                        match read_entries_from_vec(data, csv_format) {
+                            Ok(contents) => Some(FileResponse::CsvData(contents)),
                             Err(e) => Some(FileResponse::Error(e)),
                         }
                     }
                 };
             });
         }
-
-        if let Ok(mut response) = app.file_pick.try_lock() {
-            if let Some(pick) = response.take() {
-                debug!("Processing data from async file dialog...");
-                match pick {
-                    FileResponse::NoFile => debug!("Main thread registered: no file picked"),
-                    FileResponse::FileData(data) => {
-                        debug!("Main thread registered: data: {data:?}");
-                        app.data_mgr.entries = data;
-                        app.data_mgr.plot_reset_next_frame = true;
-                    }
-                    FileResponse::Error(e) => error!("Error from async file dialog: {e}"),
-                }
-                // we've consumed the response, we don't need this anymore
-                *response = None;
-            }
-        }
     }
 
     #[cfg(target_arch = "wasm32")]
@@ -137,11 +289,18 @@ impl MenuBar {
         use crate::app;
 
         if ui.button("Export").clicked() {
-            use std::io::Write;
+            use crate::csvadapter::write_entries_to_writer;
             // collect our entry data in a buffer that we'll be writing out
             let mut buf = vec![];
-            for entry in &app.data_mgr.entries {
-                writeln!(buf, "{}", entry.to_csv_string());
+            let format = app.data_mgr.export_csv_format();
+            if let Err(e) = write_entries_to_writer(
+                &app.data_mgr.entries,
+                &mut buf,
+                &format,
+                app.data_mgr.csv_version,
+            ) {
+                error!("export: couldn't build the csv data: {e}");
+                return;
             }
             wasm_bindgen_futures::spawn_local(async move {
                 // NOTE: this doesn't open a user prompt - it gets us a handle that, when written
@@ -1,13 +1,36 @@
 mod addentry;
+mod alerts;
+mod attention;
+mod budget;
+mod calendar;
+mod csvsettings;
+mod donut;
+mod drilldown;
 mod entries;
+mod filter;
 mod graph;
+mod journal;
 mod limits;
 mod mainpage;
 mod menubar;
+mod payschedule;
+mod statistics;
+mod subscriptions;
 
 pub use addentry::AddEntry;
+pub use alerts::Alerts;
+pub use attention::Attention;
+pub use budget::Budget;
+pub use csvsettings::CsvSettings;
+pub use donut::Donut;
+pub use drilldown::DrillDown;
 pub use entries::Entries;
-pub use graph::Graph;
+pub use filter::Filter;
+pub use graph::{Graph, GraphSettings};
+pub use journal::Journal;
 pub use limits::Limits;
 pub use mainpage::MainPage;
 pub use menubar::MenuBar;
+pub use payschedule::PayScheduleSettings;
+pub use statistics::Statistics;
+pub use subscriptions::Subscriptions;
@@ -0,0 +1,92 @@
+use crate::payperiod::{PaySchedule, Paydays};
+use chrono::NaiveDate;
+use egui::Ui;
+
+/// Edit when paydays are, for grouping and limiting spending by pay period
+pub struct PayScheduleSettings {
+    pub schedule: PaySchedule,
+
+    // the payday being added to a custom schedule
+    new_payday: NaiveDate,
+}
+
+impl Default for PayScheduleSettings {
+    fn default() -> Self {
+        Self {
+            schedule: PaySchedule::default(),
+            new_payday: chrono::Local::now().date_naive(),
+        }
+    }
+}
+
+impl PayScheduleSettings {
+    /// Returns true if the schedule changed
+    pub fn ui(&mut self, ui: &mut Ui) -> bool {
+        let before = self.schedule.clone();
+        let today = chrono::Local::now().date_naive();
+
+        egui::ComboBox::from_id_source("pay-schedule")
+            .selected_text(self.schedule.to_string())
+            .show_ui(ui, |ui| {
+                // keep the current schedule's details if it's picked again
+                for schedule in [
+                    PaySchedule::Biweekly { anchor: today },
+                    PaySchedule::SemiMonthly,
+                    PaySchedule::Custom(Paydays::default()),
+                ] {
+                    let selected =
+                        std::mem::discriminant(&schedule) == std::mem::discriminant(&self.schedule);
+                    let label = schedule.to_string();
+                    if ui.selectable_label(selected, label).clicked() && !selected {
+                        self.schedule = schedule;
+                    }
+                }
+            });
+
+        match &mut self.schedule {
+            PaySchedule::Biweekly { anchor } => {
+                ui.horizontal(|ui| {
+                    ui.label("Any payday:");
+                    ui.add(egui_extras::DatePickerButton::new(anchor).id_source("pay-anchor"));
+                });
+            }
+            PaySchedule::SemiMonthly => {
+                ui.label("Paid on the 1st and the 15th");
+            }
+            PaySchedule::Custom(paydays) => {
+                let mut to_remove = None;
+                for (index, payday) in paydays.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        ui.label(payday.to_string());
+                        if ui.small_button("x").clicked() {
+                            to_remove = Some(index);
+                        }
+                    });
+                }
+                if let Some(index) = to_remove {
+                    paydays.remove(index);
+                }
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui_extras::DatePickerButton::new(&mut self.new_payday)
+                            .id_source("new-payday"),
+                    );
+                    if ui
+                        .add_enabled(
+                            !paydays.contains(&self.new_payday),
+                            egui::Button::new("Add Payday"),
+                        )
+                        .clicked()
+                    {
+                        paydays.insert(self.new_payday);
+                    }
+                });
+                if paydays.len() < 2 {
+                    ui.label("Add at least two paydays to make a pay period");
+                }
+            }
+        }
+
+        self.schedule != before
+    }
+}
@@ -0,0 +1,192 @@
+use crate::backend::DataManager;
+use crate::organize::{DateRange, GroupBy};
+use crate::query::Query;
+use crate::stats::{write_stats_csv, CategoryStats, PERCENTILES};
+use chrono::NaiveDate;
+use egui::{RichText, Ui};
+use strum::IntoEnumIterator;
+
+/// Totals, averages and spreads of the spending in each category over a period, which can be exported as csv
+pub struct Statistics {
+    date_range: DateRange,
+    // only used for DateRange::Custom
+    custom_from: NaiveDate,
+    custom_to: NaiveDate,
+    // the last statistics worked out, what they were for and the data manager's change count then. They're
+    // worked out again when any of those change
+    cached: Option<(u64, Query, GroupBy<'static>, Vec<CategoryStats>)>,
+}
+
+impl Default for Statistics {
+    fn default() -> Self {
+        let today = chrono::Local::now().date_naive();
+        Self {
+            date_range: DateRange::ThisMonth,
+            custom_from: GroupBy::Month.start_of(today),
+            custom_to: today,
+            cached: None,
+        }
+    }
+}
+
+impl Statistics {
+    /// Statistics over the entries matching `filter` within the selected period. Averages per group are over
+    /// `group_by`, the graph's grouping
+    pub fn ui(
+        &mut self,
+        ui: &mut Ui,
+        data_mgr: &DataManager,
+        group_by: &GroupBy<'_>,
+        filter: &Query,
+    ) {
+        self.period_ui(ui);
+
+        let today = chrono::Local::now().date_naive();
+        let query = match self
+            .date_range
+            .bounds(today, (self.custom_from, self.custom_to))
+        {
+            Some((from, to)) => filter.clone().within(from, to),
+            None => filter.clone(),
+        };
+        let fresh = matches!(&self.cached, Some((changes, cached_query, cached_group_by, _))
+            if *changes == data_mgr.changes() && *cached_query == query && cached_group_by == group_by);
+        if !fresh {
+            let stats = data_mgr.statistics(&query, group_by);
+            self.cached = Some((
+                data_mgr.changes(),
+                query,
+                group_by.clone().into_owned(),
+                stats,
+            ));
+        }
+        let Some((_, _, _, stats)) = &self.cached else {
+            return;
+        };
+        if stats.is_empty() {
+            ui.label("Nothing was spent in this period");
+            return;
+        }
+
+        if ui.button("Export CSV").clicked() {
+            Self::export(stats, group_by);
+        }
+        ui.separator();
+
+        egui::ScrollArea::both().show(ui, |ui| {
+            Self::table(ui, stats, group_by);
+        });
+    }
+
+    fn period_ui(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Period:");
+            egui::ComboBox::from_id_source("stats-range")
+                .selected_text(self.date_range.to_string())
+                .show_ui(ui, |ui| {
+                    for range in DateRange::iter() {
+                        ui.selectable_value(&mut self.date_range, range, range.to_string());
+                    }
+                });
+            if self.date_range == DateRange::Custom {
+                ui.add(
+                    egui_extras::DatePickerButton::new(&mut self.custom_from)
+                        .id_source("stats-from"),
+                );
+                ui.label("to");
+                ui.add(
+                    egui_extras::DatePickerButton::new(&mut self.custom_to).id_source("stats-to"),
+                );
+            }
+        });
+    }
+
+    /// One row per category. Hovering a category lists its largest purchases
+    fn table(ui: &mut Ui, stats: &[CategoryStats], group_by: &GroupBy<'_>) {
+        let change =
+            |change: Option<f32>| change.map_or("-".to_string(), |c| format!("{:+.1}%", c * 100.0));
+        egui::Grid::new("stats-grid")
+            .striped(true)
+            .num_columns(10 + PERCENTILES.len())
+            .show(ui, |ui| {
+                for header in ["Category", "Total", "Count", "Mean", "Median"] {
+                    ui.label(RichText::new(header).strong());
+                }
+                for p in PERCENTILES {
+                    ui.label(RichText::new(format!("P{p}")).strong());
+                }
+                ui.label(RichText::new("MoM").strong())
+                    .on_hover_text("Change from the month before to the last month of the period");
+                ui.label(RichText::new("YoY").strong())
+                    .on_hover_text("Change from the same period a year earlier");
+                ui.label(RichText::new(format!("Per {group_by}")).strong());
+                ui.end_row();
+
+                for stat in stats {
+                    let largest: Vec<String> = stat
+                        .largest
+                        .iter()
+                        .map(|entry| {
+                            format!(
+                                "{}: {} (${:.2})",
+                                entry.date,
+                                entry.name,
+                                f32::from(entry.cost)
+                            )
+                        })
+                        .collect();
+                    ui.label(stat.category.to_string())
+                        .on_hover_text(format!("Largest purchases:\n{}", largest.join("\n")));
+                    ui.label(format!("${:.2}", stat.total));
+                    ui.label(stat.count.to_string());
+                    ui.label(format!("${:.2}", stat.mean));
+                    ui.label(format!("${:.2}", stat.median));
+                    for value in &stat.percentiles {
+                        ui.label(format!("${value:.2}"));
+                    }
+                    ui.label(change(stat.month_over_month));
+                    ui.label(change(stat.year_over_year));
+                    ui.label(format!("${:.2}", stat.per_group));
+                    ui.end_row();
+                }
+            });
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn export(stats: &[CategoryStats], group_by: &GroupBy<'_>) {
+        let Some(file_path) = rfd::FileDialog::new()
+            .add_filter("CSV", &["csv"])
+            .set_file_name("pennypilot-statistics.csv")
+            .save_file()
+        else {
+            return;
+        };
+
+        let result = write_stats_csv(stats, &group_by.to_string())
+            .and_then(|csv| std::fs::write(&file_path, csv));
+        if let Err(e) = result {
+            error!("Error exporting statistics to \"{:?}\": {}", file_path, e);
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn export(stats: &[CategoryStats], group_by: &GroupBy<'_>) {
+        let csv = match write_stats_csv(stats, &group_by.to_string()) {
+            Ok(csv) => csv,
+            Err(e) => {
+                error!("Error writing statistics: {e}");
+                return;
+            }
+        };
+        wasm_bindgen_futures::spawn_local(async move {
+            let handle = rfd::AsyncFileDialog::new().save_file().await;
+            match handle {
+                None => error!("export statistics: didn't get the save file handle"),
+                Some(handle) => match handle.write(csv.as_bytes()).await {
+                    Ok(_) => debug!("Successfully exported statistics!"),
+                    Err(e) => error!("Error while exporting statistics: {e}"),
+                },
+            }
+        });
+    }
+}
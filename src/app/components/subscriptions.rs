@@ -0,0 +1,172 @@
+use crate::backend::DataManager;
+use crate::entry::Entry;
+use crate::subscription::{RecurringRule, RuleStatus, Subscription};
+use egui::{RichText, Ui};
+
+/// Subscriptions found in the spending history. Each one can be confirmed into a recurring rule, or marked as
+/// cancelled so its missing charges stop being flagged
+#[derive(serde::Deserialize, serde::Serialize, Clone, Default)]
+#[serde(default)] // if we add new fields, give them default values when deserializing old state
+pub struct Subscriptions {
+    rules: Vec<RecurringRule>,
+
+    // what the last scan found, and the data manager's change count then. Entries are scanned again when it moves
+    #[serde(skip)]
+    detected: Vec<Subscription>,
+    #[serde(skip)]
+    scanned: Option<u64>,
+}
+
+impl Subscriptions {
+    pub fn ui(&mut self, ui: &mut Ui, data_mgr: &DataManager) {
+        if self.scanned != Some(data_mgr.changes()) {
+            self.detected = data_mgr.subscriptions();
+            self.scanned = Some(data_mgr.changes());
+        }
+
+        let active: f32 = self
+            .rules
+            .iter()
+            .filter(|rule| rule.status == RuleStatus::Active)
+            .map(|rule| rule.amount * rule.cadence.per_year())
+            .sum();
+        ui.label(format!(
+            "Confirmed subscriptions cost ${:.2} a year",
+            active
+        ));
+        ui.separator();
+
+        if self.detected.is_empty() {
+            ui.label("(No subscriptions found)");
+        }
+        let mut changed: Option<RecurringRule> = None;
+        // the rule to drop, by its index
+        let mut forgotten: Option<usize> = None;
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            egui::Grid::new("subscriptions-grid")
+                .striped(true)
+                .num_columns(7)
+                .show(ui, |ui| {
+                    for header in [
+                        "Name",
+                        "Category",
+                        "Cadence",
+                        "Price",
+                        "Next Expected",
+                        "Per Year",
+                    ] {
+                        ui.label(RichText::new(header).strong());
+                    }
+                    ui.end_row();
+
+                    for subscription in &self.detected {
+                        ui.label(&subscription.name);
+                        ui.label(subscription.category.to_string());
+                        ui.label(subscription.cadence.to_string());
+                        let history: Vec<String> = subscription
+                            .prices
+                            .iter()
+                            .map(|(date, price)| format!("{}: ${:.2}", date, price))
+                            .collect();
+                        let changes = subscription.prices.len() - 1;
+                        ui.label(if changes > 0 {
+                            format!("${:.2} ({} changes)", subscription.amount, changes)
+                        } else {
+                            format!("${:.2}", subscription.amount)
+                        })
+                        .on_hover_text(format!("Price history:\n{}", history.join("\n")));
+                        ui.label(subscription.next_expected().to_string());
+                        ui.label(format!("${:.2}", subscription.annual_cost()));
+
+                        let rule = self.rules.iter().position(|rule| rule.is_for(subscription));
+                        ui.horizontal(|ui| {
+                            match rule.map(|index| (index, self.rules[index].status)) {
+                                Some((_, RuleStatus::Active)) => {
+                                    ui.label(RuleStatus::Active.to_string());
+                                    if ui.small_button("Mark Cancelled").clicked() {
+                                        changed = Some(RecurringRule::new(
+                                            subscription,
+                                            RuleStatus::Cancelled,
+                                        ));
+                                    }
+                                }
+                                Some((index, RuleStatus::Cancelled)) => {
+                                    ui.label(RuleStatus::Cancelled.to_string());
+                                    if ui.small_button("Undo").clicked() {
+                                        forgotten = Some(index);
+                                    }
+                                }
+                                None => {
+                                    if ui
+                                        .small_button("Confirm")
+                                        .on_hover_text("Keep it as a recurring rule")
+                                        .clicked()
+                                    {
+                                        changed = Some(RecurringRule::new(
+                                            subscription,
+                                            RuleStatus::Active,
+                                        ));
+                                    }
+                                    if ui
+                                        .small_button("Cancelled")
+                                        .on_hover_text("Stop flagging its missing charges")
+                                        .clicked()
+                                    {
+                                        changed = Some(RecurringRule::new(
+                                            subscription,
+                                            RuleStatus::Cancelled,
+                                        ));
+                                    }
+                                }
+                            }
+                        });
+                        ui.end_row();
+                    }
+                });
+
+            // rules whose charges don't look like a subscription anymore, e.g. after entries were deleted
+            let others: Vec<(usize, &RecurringRule)> = self
+                .rules
+                .iter()
+                .enumerate()
+                .filter(|(_, rule)| !self.detected.iter().any(|s| rule.is_for(s)))
+                .collect();
+            if !others.is_empty() {
+                ui.separator();
+                ui.label(RichText::new("Other Rules").strong());
+                for (index, rule) in others {
+                    ui.horizontal(|ui| {
+                        ui.label(format!(
+                            "{} ({}): ${:.2} {}, {}",
+                            rule.name, rule.category, rule.amount, rule.cadence, rule.status
+                        ));
+                        if ui.small_button("Forget").clicked() {
+                            forgotten = Some(index);
+                        }
+                    });
+                }
+            }
+        });
+
+        if let Some(index) = forgotten {
+            self.rules.remove(index);
+        }
+        if let Some(rule) = changed {
+            self.rules
+                .retain(|r| (&r.key, r.category) != (&rule.key, rule.category));
+            self.rules.push(rule);
+        }
+    }
+
+    /// The subscriptions the user confirmed or marked as cancelled
+    pub fn rules(&self) -> &[RecurringRule] {
+        &self.rules
+    }
+
+    /// Whether `entry` is a charge of a subscription marked as cancelled
+    pub fn is_cancelled(&self, entry: &Entry) -> bool {
+        self.rules
+            .iter()
+            .any(|rule| rule.status == RuleStatus::Cancelled && rule.matches(entry))
+    }
+}
@@ -13,6 +13,9 @@ impl eframe::App for App {
 
     /// Called each time the UI needs repainting, which may be many times per second.
     fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        #[cfg(target_arch = "wasm32")]
+        self.process_file_pick();
+
         egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
             MenuBar::add(self, ui, frame);
         });
@@ -21,6 +24,10 @@ impl eframe::App for App {
             MainPage::add(self, ui, frame);
             self.show_windows(ui);
         });
+
+        if self.alerts.toasts(ctx) {
+            self.window_state.alerts_open = true;
+        }
     }
 
     // Screenshot not supported on wasm. See: https://docs.rs/eframe/latest/eframe/struct.Frame.html#method.screenshot
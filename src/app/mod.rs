@@ -1,12 +1,18 @@
 use crate::backend::DataManager;
 
+mod bundle;
 mod components;
 mod egui_app;
 
-use components::{AddEntry, Entries, Graph, Limits};
+use components::{
+    AddEntry, Alerts, Attention, Budget, CsvSettings, Donut, DrillDown, Entries, Filter, Graph,
+    Journal, Limits, PayScheduleSettings, Statistics, Subscriptions,
+};
 use egui::{vec2, Ui, Window};
 use strum_macros::EnumIter;
 
+#[cfg(target_arch = "wasm32")]
+use crate::csvadapter::CsvContents;
 #[cfg(target_arch = "wasm32")]
 use crate::entry::Entry;
 #[cfg(target_arch = "wasm32")]
@@ -38,6 +44,17 @@ pub struct WindowState {
     // pub settings_open: bool,
     pub spending_limits_open: bool,
     pub graph_settings_open: bool,
+    pub journal_open: bool,
+    pub csv_settings_open: bool,
+    pub alerts_open: bool,
+    pub budget_open: bool,
+    pub filter_open: bool,
+    pub drill_down_open: bool,
+    pub donut_open: bool,
+    pub pay_schedule_open: bool,
+    pub statistics_open: bool,
+    pub attention_open: bool,
+    pub subscriptions_open: bool,
 
     #[cfg(target_arch = "wasm32")]
     pub web_notice_open: bool,
@@ -55,6 +72,17 @@ impl Default for WindowState {
             // settings_open: false,
             spending_limits_open: false,
             graph_settings_open: false,
+            journal_open: false,
+            csv_settings_open: false,
+            alerts_open: false,
+            budget_open: false,
+            filter_open: false,
+            drill_down_open: false,
+            donut_open: false,
+            pay_schedule_open: false,
+            statistics_open: false,
+            attention_open: false,
+            subscriptions_open: false,
 
             #[cfg(target_arch = "wasm32")]
             web_notice_open: true,
@@ -68,6 +96,8 @@ impl Default for WindowState {
 pub enum FileResponse {
     NoFile,
     FileData(Vec<Entry>),
+    CsvData(CsvContents),
+    Bundle(Box<bundle::Bundle>),
     Error(Box<dyn Error>),
 }
 
@@ -76,9 +106,24 @@ pub struct App {
     pub window_state: WindowState,
 
     pub spending_limits: Limits,
+    pub alerts: Alerts,
+    // entries that look like typos, double charges or fraud
+    pub attention: Attention,
+    // recurring charges found in the entries, and the ones the user confirmed or cancelled
+    pub subscriptions: Subscriptions,
+    pub budget: Budget,
     pub entry_view: Entries,
     pub add_entry_view: AddEntry,
     pub graph: Graph,
+    // the entries behind the last bar clicked in the graph
+    pub drill_down: Option<DrillDown>,
+    pub donut: Donut,
+    pub statistics: Statistics,
+    // narrows down what the entry view and graph show
+    pub filter: Filter,
+    pub journal: Journal,
+    // shared by the graph and spending limits for grouping by pay period
+    pub pay_schedule: PayScheduleSettings,
 
     #[cfg(target_arch = "wasm32")]
     // Handle asynchronous file import on wasm
@@ -100,10 +145,20 @@ impl Default for App {
         Self {
             data_mgr: backend,
             graph: Graph::default(),
+            drill_down: None,
+            donut: Donut::default(),
+            statistics: Statistics::default(),
+            filter: Filter::default(),
+            journal: Journal::default(),
+            pay_schedule: PayScheduleSettings::default(),
             add_entry_view: AddEntry::default(),
             window_state: WindowState::default(),
             entry_view,
             spending_limits: Limits::default(),
+            alerts: Alerts::default(),
+            attention: Attention::default(),
+            subscriptions: Subscriptions::default(),
+            budget: Budget::default(),
             #[cfg(target_arch = "wasm32")]
             file_pick: Arc::new(Mutex::new(None)),
         }
@@ -138,7 +193,31 @@ impl App {
             .default_size(vec2(200.0, 200.0))
             .vscroll(false)
             .show(ui.ctx(), |ui| {
-                self.entry_view.ui(ui, &mut self.data_mgr);
+                self.entry_view
+                    .ui(ui, &mut self.data_mgr, &self.filter.query);
+            });
+
+        if let Some(drill_down) = &self.drill_down {
+            let mut selected = None;
+            Window::new("Drill Down")
+                .open(&mut self.window_state.drill_down_open)
+                .default_size(vec2(250.0, 300.0))
+                .vscroll(false)
+                .show(ui.ctx(), |ui| {
+                    selected = drill_down.ui(ui, &self.data_mgr);
+                });
+            if let Some(entry) = selected {
+                self.entry_view.highlight(entry);
+                self.window_state.entry_open = true;
+            }
+        }
+
+        Window::new("Filter")
+            .open(&mut self.window_state.filter_open)
+            .default_size(vec2(200.0, 200.0))
+            .vscroll(false)
+            .show(ui.ctx(), |ui| {
+                self.filter.ui(ui, &self.data_mgr);
             });
 
         Window::new("Spending Limits")
@@ -146,7 +225,81 @@ impl App {
             .default_size(vec2(200.0, 400.0))
             .vscroll(false)
             .show(ui.ctx(), |ui| {
-                self.spending_limits.ui(ui, &self.data_mgr);
+                self.spending_limits.ui(
+                    ui,
+                    &self.data_mgr,
+                    &self.pay_schedule.schedule,
+                    &self.graph.pace_query(&self.filter.query),
+                );
+            });
+
+        let mut drill_down = None;
+        Window::new("Alerts")
+            .open(&mut self.window_state.alerts_open)
+            .default_size(vec2(300.0, 300.0))
+            .vscroll(false)
+            .show(ui.ctx(), |ui| {
+                drill_down = self.alerts.ui(ui);
+            });
+        if let Some(drill_down) = drill_down {
+            debug!("Drilling down into {}", drill_down.title);
+            self.drill_down = Some(drill_down);
+            self.window_state.drill_down_open = true;
+        }
+
+        let mut selected = None;
+        Window::new("Attention")
+            .open(&mut self.window_state.attention_open)
+            .default_size(vec2(350.0, 300.0))
+            .vscroll(false)
+            .show(ui.ctx(), |ui| {
+                selected = self.attention.ui(ui, &self.data_mgr, &self.subscriptions);
+            });
+        if let Some(entry) = selected {
+            self.entry_view.highlight(entry);
+            self.window_state.entry_open = true;
+        }
+
+        Window::new("Subscriptions")
+            .open(&mut self.window_state.subscriptions_open)
+            .default_size(vec2(550.0, 300.0))
+            .vscroll(false)
+            .show(ui.ctx(), |ui| {
+                self.subscriptions.ui(ui, &self.data_mgr);
+            });
+
+        Window::new("Envelope Budget")
+            .open(&mut self.window_state.budget_open)
+            .default_size(vec2(400.0, 300.0))
+            .vscroll(false)
+            .show(ui.ctx(), |ui| {
+                self.budget.ui(ui, &self.data_mgr, &self.spending_limits);
+            });
+
+        Window::new("Category Share")
+            .open(&mut self.window_state.donut_open)
+            .default_size(vec2(450.0, 250.0))
+            .vscroll(false)
+            .show(ui.ctx(), |ui| {
+                self.donut.ui(
+                    ui,
+                    &self.data_mgr,
+                    &mut self.graph.settings,
+                    &self.filter.query,
+                );
+            });
+
+        Window::new("Statistics")
+            .open(&mut self.window_state.statistics_open)
+            .default_size(vec2(600.0, 300.0))
+            .vscroll(false)
+            .show(ui.ctx(), |ui| {
+                self.statistics.ui(
+                    ui,
+                    &self.data_mgr,
+                    &self.graph.settings.group_by(&self.pay_schedule.schedule),
+                    &self.filter.query,
+                );
             });
 
         Window::new("Graph Settings")
@@ -154,7 +307,41 @@ impl App {
             .default_size(vec2(200.0, 400.0))
             .vscroll(false)
             .show(ui.ctx(), |ui| {
-                self.graph.settings.ui(ui);
+                self.graph.settings.ui(ui, &self.pay_schedule.schedule);
+            });
+
+        let mut schedule_changed = false;
+        Window::new("Pay Schedule")
+            .open(&mut self.window_state.pay_schedule_open)
+            .default_size(vec2(200.0, 100.0))
+            .vscroll(false)
+            .show(ui.ctx(), |ui| {
+                schedule_changed = self.pay_schedule.ui(ui);
+            });
+        // pay periods moved, so the graph might cover different dates
+        if schedule_changed {
+            self.data_mgr.plot_reset_next_frame = true;
+        }
+
+        Window::new("CSV Format")
+            .open(&mut self.window_state.csv_settings_open)
+            .default_size(vec2(200.0, 100.0))
+            .vscroll(false)
+            .show(ui.ctx(), |ui| {
+                CsvSettings::add(ui, &mut self.data_mgr);
+            });
+
+        Window::new("Plain-Text Journal")
+            .open(&mut self.window_state.journal_open)
+            .default_size(vec2(200.0, 100.0))
+            .vscroll(false)
+            .show(ui.ctx(), |ui| {
+                self.journal.ui(
+                    ui,
+                    &mut self.data_mgr,
+                    #[cfg(target_arch = "wasm32")]
+                    &self.file_pick,
+                );
             });
 
         #[cfg(target_arch = "wasm32")]
@@ -166,4 +353,28 @@ impl App {
                 ui.label("Hey! Thanks for using PennyPilot on the web.\nNote: You MUST export data via file -> export in order for it to be saved!");
             });
     }
+
+    /// Load whatever the user picked in an async file dialog once it's ready. Called every frame so that a pick
+    /// from any window gets processed, not just the menu that started it
+    #[cfg(target_arch = "wasm32")]
+    pub fn process_file_pick(&mut self) {
+        // take the response out first so the lock isn't held while the app is updated
+        let pick = match self.file_pick.try_lock() {
+            Ok(mut response) => response.take(),
+            Err(_) => None,
+        };
+        if let Some(pick) = pick {
+            debug!("Processing data from async file dialog...");
+            match pick {
+                FileResponse::NoFile => debug!("Main thread registered: no file picked"),
+                FileResponse::FileData(data) => {
+                    debug!("Main thread registered: data: {data:?}");
+                    self.data_mgr.load_entries(data);
+                }
+                FileResponse::CsvData(contents) => self.data_mgr.load_csv_contents(contents),
+                FileResponse::Bundle(bundle) => self.import_bundle(*bundle),
+                FileResponse::Error(e) => error!("Error from async file dialog: {e}"),
+            }
+        }
+    }
 }
@@ -1,15 +1,37 @@
-use chrono::{Datelike, NaiveDate};
-use std::collections::BTreeMap;
+use chrono::{Datelike, Months, NaiveDate};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
 use std::path::PathBuf;
+use std::rc::Rc;
 use strum::IntoEnumIterator;
 
+use crate::anomaly::{missing_charges, scan, Flag};
+use crate::cache::{CostMapCache, CostMapView, CostSums};
 use crate::category::Category;
 use crate::csvadapter::*;
-use crate::entry::{Cost, Entry};
+use crate::csvformat::CsvFormat;
+use crate::entry::Entry;
+use crate::forecast::{predict, Forecast, HISTORY_MONTHS, RECURRING_MONTHS};
+use crate::limit::{Limit, LimitStatus};
 use crate::organize::*;
-
-type Comparator = Box<dyn Fn(&Entry, &Entry) -> std::cmp::Ordering>;
-type CostMap = BTreeMap<Category, BTreeMap<NaiveDate, f32>>;
+use crate::payperiod::PaySchedule;
+use crate::query::{Query, QueryResult};
+use crate::stats::{change, percentile, CategoryStats, LARGEST, PERCENTILES};
+use crate::store::{EntryKey, EntryStore};
+use crate::subscription::{detect, normalize, RecurringRule, Subscription};
+
+pub type CostMap = BTreeMap<Category, BTreeMap<NaiveDate, f32>>;
+
+// more than enough for every forecast shown at once: the pace chart's plus one per category with a limit
+const FORECAST_CAPACITY: usize = 64;
+
+/// Forecasts that were already made, along with what they were made for and the change count of the entries then
+#[derive(Default)]
+struct ForecastCache {
+    changes: u64,
+    // oldest first
+    forecasts: Vec<(Query, Category, NaiveDate, Option<Forecast>)>,
+}
 
 #[derive(serde::Deserialize, serde::Serialize)]
 #[serde(default)] // if we add new fields, give them default values when deserializing old state
@@ -17,11 +39,40 @@ pub struct DataManager {
     // entries are loaded from/written to this file on native
     pub active_file: Option<PathBuf>,
 
+    // alternatively, entries are loaded from/written to one file per year. Only one of these is set at a time
+    pub active_ledger: Option<YearLedger>,
+
     pub sort_by: SortBy,
 
+    // how dates and costs are written in csv files. None means detect it from each file that's read
+    pub csv_format: Option<CsvFormat>,
+
+    #[serde(skip)]
+    /// The csv layout version entries are written with. It's the version of the file they were read from, so that
+    /// the file stays readable by the build that wrote it until the user chooses to upgrade it
+    pub csv_version: u32,
+
+    #[serde(skip)]
+    /// The format of the most recently read csv file. Exports use it unless `csv_format` overrides it, so a file
+    /// is written back the way it was read
+    pub detected_csv_format: Option<CsvFormat>,
+
     #[serde(skip)]
     // We don't serialize entries because the underlying data could have changed, so we reload it
-    pub entries: Vec<Entry>,
+    // Once cost maps have been built, add and remove entries with add_entry/remove_entry so they stay up to date
+    pub entries: EntryStore,
+
+    #[serde(skip)]
+    // goes up every time the entries change, so views can tell when what they worked out from them is stale
+    changes: u64,
+
+    #[serde(skip)]
+    // cost maps are asked for every frame, so keep them around rather than rescanning every entry
+    cost_maps: RefCell<CostMapCache>,
+
+    #[serde(skip)]
+    // forecasts are asked for every frame too. They're dropped once the entries change
+    forecasts: RefCell<ForecastCache>,
 
     #[serde(skip)]
     /// Was data loaded recently? This is meant to share state with the rest of the app.
@@ -32,41 +83,65 @@ pub struct DataManager {
 impl Default for DataManager {
     fn default() -> Self {
         Self {
-            entries: vec![],
+            entries: EntryStore::default(),
             sort_by: SortBy::Date,
             active_file: None,
+            active_ledger: None,
+            csv_format: None,
+            detected_csv_format: None,
+            csv_version: CSV_VERSION,
             plot_reset_next_frame: false,
+            changes: 0,
+            cost_maps: RefCell::default(),
+            forecasts: RefCell::default(),
         }
     }
 }
 
-// Basic Accessors
 impl DataManager {
-    // Returns either a normal or reverse iterator depending on the 'reverse' parameter
-    // This allows the backend to not have to worry about whether the entry list is reversed
-    // iter() and iter.rev() are different types, so they're wrapped in a Box so that the return
-    // type size is known at compile time
-    // overall this makes for a clean API where you can get the normal or reversed list with 1
-    // parameter instead of 2 functions or keeping extra state (and doing extra sorting with certain
-    // operations) in the backend.
-    // this is meant to be used like:
-    // for entry: &Entry in backend.get_entries_iter(reverse)
-    pub fn get_entries_iter(&self, reverse: bool) -> Box<dyn Iterator<Item = &Entry> + '_> {
-        if reverse {
-            Box::new(self.entries.iter().rev())
-        } else {
-            Box::new(self.entries.iter())
-        }
+    /// The format csv files are written in
+    pub fn export_csv_format(&self) -> CsvFormat {
+        self.csv_format
+            .or(self.detected_csv_format)
+            .unwrap_or_default()
+    }
+
+    /// Use entries read from csv, remembering how they were laid out so they're written back the same way
+    fn set_csv_contents(&mut self, contents: CsvContents) {
+        info!(
+            "Read csv data (version {}) with {}",
+            contents.version, contents.format
+        );
+        self.entries = contents.entries.into();
+        self.changes += 1;
+        self.cost_maps.get_mut().clear();
+        self.detected_csv_format = Some(contents.format);
+        self.csv_version = contents.version;
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    /// Is the data written with an older csv layout than the newest one?
+    pub fn csv_outdated(&self) -> bool {
+        self.csv_version < CSV_VERSION
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    /// Rewrite the active file (or ledger) with the newest csv layout
+    pub fn upgrade_csv(&mut self) {
+        debug!(
+            "Upgrading csv from version {} to {}",
+            self.csv_version, CSV_VERSION
+        );
+        self.csv_version = CSV_VERSION;
+        self.write_entries_to_csv(None);
     }
-}
 
-impl DataManager {
     pub fn read_entries_from_csv(&mut self, file_path: PathBuf) {
-        let result = read_entries_from_file(&file_path);
+        let result = read_entries_from_file(&file_path, self.csv_format);
 
         match result {
-            Ok(entries) => {
-                self.entries = entries;
+            Ok(contents) => {
+                self.set_csv_contents(contents);
                 // set some flag so we know to reset the plot
                 self.plot_reset_next_frame = true;
             }
@@ -78,11 +153,48 @@ impl DataManager {
             self.active_file = Some(file_path);
             // self.serialize_backend();
         }
+        self.active_ledger = None;
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    /// Load every year file matching `pattern` (a directory or a glob like `ledgers/*.csv`) as one dataset.
+    /// Until another file is imported, changes are routed back to the file matching each entry's year.
+    pub fn read_entries_from_dir(&mut self, pattern: PathBuf) {
+        match YearLedger::open(pattern.clone(), self.csv_format) {
+            Ok((ledger, contents)) => {
+                self.set_csv_contents(contents);
+                self.plot_reset_next_frame = true;
+                self.active_ledger = Some(ledger);
+                self.active_file = None;
+            }
+            Err(e) => error!("Error reading entries from \"{:?}\": {}", pattern, e),
+        }
+    }
+
+    /// Replace the entries with ones read from a csv file that isn't on disk (an import on the web).
+    /// There's nothing to write changes back to until the user exports
+    #[cfg(target_arch = "wasm32")]
+    pub fn load_csv_contents(&mut self, contents: CsvContents) {
+        self.set_csv_contents(contents);
+        self.plot_reset_next_frame = true;
+    }
+
+    /// Replace the entries with ones that didn't come from a csv file (a journal or a bundle).
+    /// There's nothing to write changes back to until the user exports
+    pub fn load_entries(&mut self, entries: Vec<Entry>) {
+        self.entries = entries.into();
+        self.changes += 1;
+        self.cost_maps.get_mut().clear();
+        self.csv_version = CSV_VERSION;
+        self.plot_reset_next_frame = true;
+        self.active_file = None;
+        self.active_ledger = None;
     }
 
     /// Write entries to CSV
     ///
-    /// If `file_path` is None, attempts to write to the active file. If no active file is set, does nothing
+    /// If `file_path` is None, attempts to write to the active file (or every file of the active ledger). If neither
+    /// is set, does nothing
     /// If `file_path` is specified, will update the active file if they don't match
     pub fn write_entries_to_csv(&mut self, file_path: Option<PathBuf>) {
         let file_path = match file_path {
@@ -90,6 +202,10 @@ impl DataManager {
             None => {
                 match &self.active_file {
                     Some(path) => path.clone(),
+                    None if self.active_ledger.is_some() => {
+                        self.write_ledger(None);
+                        return;
+                    }
                     None => {
                         debug!("write entries to CSV with unspecified path & no active file - skipping");
                         return;
@@ -98,60 +214,79 @@ impl DataManager {
             }
         };
 
-        if let Err(e) = write_entries_to_csv(&self.entries, &file_path) {
+        if let Err(e) = write_entries_to_csv(
+            &self.entries,
+            &file_path,
+            &self.export_csv_format(),
+            self.csv_version,
+        ) {
             error!("Error writing entries to CSV: {}", e);
         }
 
         if self.active_file.as_ref() != Some(&file_path) {
             self.active_file = Some(file_path);
+            self.active_ledger = None;
             // self.serialize_backend();
         }
     }
 
+    /// Write entries back to the active ledger. Only `year`'s file is written if it's specified, otherwise all of them
+    fn write_ledger(&mut self, year: Option<i32>) {
+        let format = self.export_csv_format();
+        let Some(ledger) = self.active_ledger.as_mut() else {
+            return;
+        };
+        let result = match year {
+            Some(year) => ledger.write_year(&self.entries, year, &format, self.csv_version),
+            None => ledger.write_all(&self.entries, &format, self.csv_version),
+        };
+        if let Err(e) = result {
+            error!(
+                "Error writing entries to ledger {:?}: {}",
+                ledger.pattern, e
+            );
+        }
+    }
+
     // some data changed in entries (as a result of UI interaction)
-    // for now, this is just called on add/delete
-    fn data_changed(&mut self) {
-        debug!("Data changed, calling write_entries_to_csv");
-        self.write_entries_to_csv(None);
+    // for now, this is just called on add/delete. `date` is the date of the entry that changed
+    fn data_changed(&mut self, date: NaiveDate) {
+        if self.active_ledger.is_some() {
+            debug!("Data changed, writing {} to the ledger", date.year());
+            self.write_ledger(Some(date.year()));
+        } else {
+            debug!("Data changed, calling write_entries_to_csv");
+            self.write_entries_to_csv(None);
+        }
     }
 
     pub fn add_entry(&mut self, entry: Entry) {
-        self.entries.push(entry);
-
-        // what were we sorted by? ensure that we're still sorted
-        self.sort_entries(self.sort_by);
+        let date = entry.date;
+        self.cost_maps.get_mut().add_entry(&entry);
+        self.entries.insert(entry);
+        self.changes += 1;
 
-        self.data_changed();
+        self.data_changed(date);
     }
 
-    pub fn remove_entry_pos(&mut self, index: usize) {
-        self.entries.remove(index);
+    pub fn remove_entry(&mut self, key: EntryKey) {
+        let Some(entry) = self.entries.remove(key) else {
+            return;
+        };
+        self.cost_maps.get_mut().remove_entry(&entry);
+        self.changes += 1;
 
-        self.data_changed();
+        self.data_changed(entry.date);
     }
 
-    pub fn sort_entries(&mut self, sort_by: SortBy) {
-        let comparator: Comparator = match sort_by {
-            SortBy::Cost => Box::new(|a, b| {
-                Into::<f32>::into(a.cost)
-                    .partial_cmp(&b.cost.into())
-                    .unwrap()
-            }),
-            SortBy::Date => Box::new(|a, b| {
-                let date_a = &a.date;
-                let date_b = &b.date;
-
-                if date_a.year() != date_b.year() {
-                    date_a.year().cmp(&date_b.year())
-                } else if date_a.month() != date_b.month() {
-                    date_a.month().cmp(&date_b.month())
-                } else {
-                    date_a.day().cmp(&date_b.day())
-                }
-            }),
-        };
-        self.entries.sort_by(|a, b| comparator(a, b));
+    /// How many times the entries have been added to, removed from or replaced. Anything worked out from the entries
+    /// is stale once this changes
+    pub fn changes(&self) -> u64 {
+        self.changes
+    }
 
+    /// Entries are always stored by date, so this only changes the order `sorted_query` lists them in
+    pub fn sort_entries(&mut self, sort_by: SortBy) {
         // only serialize if it's changed
         if self.sort_by != sort_by {
             self.sort_by = sort_by;
@@ -162,92 +297,322 @@ impl DataManager {
     /// Builds an ordered mapping for each date to the total spent on that date.
     /// order the category map so it's always sorted the same. If you use a hashmap it's in a different order for every
     /// frame, which makes them get a different color
-    pub fn cost_map(&self, group_by: GroupBy, categories: Vec<Category>) -> CostMap {
-        if self.entries.is_empty() {
+    /// Only entries matching `query` are counted, and the map only spans the query's dates. Without any, it
+    /// spans from the first entry to the last. Maps are cached for `view`, so asking for the same one again is cheap
+    pub fn cost_map(&self, view: CostMapView, group_by: GroupBy<'_>, query: &Query) -> Rc<CostMap> {
+        let mut maps = self.cost_maps(view, group_by, std::slice::from_ref(query));
+        maps.remove(0)
+    }
+
+    /// `cost_map` for each of `queries`, for a view that shows several at once
+    pub fn cost_maps(
+        &self,
+        view: CostMapView,
+        group_by: GroupBy<'_>,
+        queries: &[Query],
+    ) -> Vec<Rc<CostMap>> {
+        self.cost_maps
+            .borrow_mut()
+            .get_or_build(view, &group_by, queries, |query| {
+                self.build_cost_sums(&group_by, query)
+            })
+    }
+
+    #[cfg(test)]
+    fn build_cost_map(&self, group_by: &GroupBy<'_>, query: &Query) -> CostMap {
+        crate::cache::to_cost_map(&self.build_cost_sums(group_by, query))
+    }
+
+    fn build_cost_sums(&self, group_by: &GroupBy<'_>, query: &Query) -> CostSums {
+        let Some((first, last)) = self.entries.date_range() else {
             return BTreeMap::new();
-        }
+        };
+        let first = query.from.unwrap_or(first);
+        let last = query.to.unwrap_or(last);
         // build the cost map with zerod entries accordingly
-        let mut map = self.zero_cost_map(group_by);
+        let mut map = self.zero_cost_map(group_by, first, last);
 
         // now track a sum for each date
-        for entry in self.get_entries_iter(false) {
-            if !categories.contains(&entry.category) {
-                continue; // skip anything that wasn't asked for
-            }
+        for entry in self.query(query).entries() {
             // scale the date based on the grouping. if grouping by month, all entries are counted for the first day of the month
             // if grouping by year, all entries are counted for the first day of the year. This has to match how zero_cost_map
             // builds the map.
-            let scaled_date = match group_by {
-                GroupBy::Day => entry.date,
-                GroupBy::Month => {
-                    NaiveDate::from_ymd_opt(entry.date.year(), entry.date.month(), 1).unwrap()
-                }
-                GroupBy::Year => NaiveDate::from_ymd_opt(entry.date.year(), 1, 1).unwrap(),
-            };
+            let scaled_date = group_by.start_of(entry.date);
             let inner_map = map.entry(entry.category).or_default();
             let sum = inner_map.entry(scaled_date).or_insert(0.0);
-            let cost: f32 = entry.cost.into();
-            *sum += cost;
+            *sum += f32::from(entry.cost) as f64;
         }
         map
     }
 
-    // get the total spent in a given category given a category, month & year
-    // NOTE: the 'day' component of 'date' is ignored, it's just simpler to have 1 parameter
-    pub fn monthly_cost(&self, _category: Category, _date: NaiveDate) -> f32 {
-        0.0 // TODO: implement me
+    /// The running total of the entries matching `query` through each day of each month in `months` (given by the
+    /// day they start on)
+    pub fn cumulative_by_day(&self, query: &Query, months: &[NaiveDate]) -> Vec<Vec<f32>> {
+        let ranges: Vec<(NaiveDate, NaiveDate)> = months
+            .iter()
+            .map(|&month| GroupBy::Month.range(month))
+            .collect();
+        let queries: Vec<Query> = ranges
+            .iter()
+            .map(|&(start, end)| query.clone().within(start, end))
+            .collect();
+        let maps = self.cost_maps(CostMapView::Pace, GroupBy::Day, &queries);
+        ranges
+            .iter()
+            .zip(maps)
+            .map(|(&(start, end), map)| {
+                start
+                    .iter_days()
+                    .take_while(|&day| day <= end)
+                    .scan(0.0, |total, day| {
+                        *total += map
+                            .values()
+                            .filter_map(|dates| dates.get(&day))
+                            .sum::<f32>();
+                        Some(*total)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// The first day anything in `query`'s categories was spent on. Its other conditions are left out, so this is
+    /// cheap enough to ask for every frame. None if there's nothing in them
+    pub fn first_date(&self, query: &Query) -> Option<NaiveDate> {
+        match &query.categories {
+            Some(categories) => categories
+                .iter()
+                .filter_map(|&category| self.entries.first_date(category))
+                .min(),
+            None => self.entries.first_date(Category::All),
+        }
     }
 
-    // TODO: think of a better design for storing entries so I don't have to do this?
-    // could cache first and last to make it easier.
-    // this is necessary because the UI can sort entries by cost and we sort the backend vect accordingly
-    // but we need the first and last dates to generate zero_cost_map()
-    fn entries_date_extremes(&self) -> (Option<&Entry>, Option<&Entry>) {
-        let mut earliest: Option<&Entry> = None;
-        let mut latest: Option<&Entry> = None;
+    /// The total spent in `category` during the month of `date`. `Category::All` counts every category
+    /// NOTE: the 'day' component of 'date' is ignored, it's just simpler to have 1 parameter
+    pub fn monthly_cost(&self, category: Category, date: NaiveDate) -> f32 {
+        let (start, end) = GroupBy::Month.range(date);
+        self.entries.total(category, start, end)
+    }
 
-        for entry in &self.entries {
-            match earliest {
-                None => earliest = Some(entry),
-                Some(e) if entry.date < e.date => earliest = Some(entry),
-                _ => {}
-            }
-            match latest {
-                None => latest = Some(entry),
-                Some(e) if entry.date > e.date => latest = Some(entry),
-                _ => {}
+    /// Every entry that matches `query`, plus aggregates over them. They're listed in date order
+    pub fn query(&self, query: &Query) -> QueryResult<'_> {
+        self.entries.query(query, SortBy::Date)
+    }
+
+    /// Like `query`, but listed in `sort_by` order, the way the user picked to see them
+    pub fn sorted_query(&self, query: &Query) -> QueryResult<'_> {
+        self.entries.query(query, self.sort_by)
+    }
+
+    /// How much of `limit` was spent in `category` during the period that contains `date`, with pay periods
+    /// following `pay_schedule`. None if the limit has a custom period that doesn't contain it
+    pub fn limit_status(
+        &self,
+        category: Category,
+        limit: &Limit,
+        date: NaiveDate,
+        pay_schedule: &PaySchedule,
+    ) -> Option<LimitStatus> {
+        let (start, end) = limit.range_containing(date, pay_schedule)?;
+        Some(LimitStatus {
+            start,
+            end,
+            spent: self.entries.total(category, start, end),
+            amount: limit.amount,
+        })
+    }
+
+    /// Statistics for each category with entries matching `query`, followed by one over every category. Changes
+    /// compare against the same query moved back a month or a year, and the per group average is over `group_by`
+    pub fn statistics(&self, query: &Query, group_by: &GroupBy<'_>) -> Vec<CategoryStats> {
+        let Some((first, last)) = self.entries.date_range() else {
+            return vec![];
+        };
+        let from = query.from.unwrap_or(first);
+        let to = query.to.unwrap_or(last);
+        let groups = group_by.starts(from, to).len().max(1);
+        // what the query would match from `from` to `to` in `category`
+        let spent = |category: Category, from: NaiveDate, to: NaiveDate| {
+            let moved = Query {
+                from: Some(from),
+                to: Some(to),
+                ..query.clone()
+            };
+            self.query(&moved.category(category)).total()
+        };
+
+        let result = self.query(query);
+        let mut by_category: BTreeMap<Category, Vec<&Entry>> = BTreeMap::new();
+        for entry in result.entries() {
+            by_category.entry(entry.category).or_default().push(entry);
+        }
+        by_category.insert(Category::All, result.entries().collect());
+
+        let month = GroupBy::Month.start_of(to);
+        let (previous_from, previous_to) = GroupBy::Month.range(month - Months::new(1));
+        let year_ago = |date: NaiveDate| date - Months::new(12);
+        // All sorts last, so the total over every category comes after the others
+        by_category
+            .into_iter()
+            .filter(|(_, entries)| !entries.is_empty())
+            .map(|(category, mut entries)| {
+                entries.sort_by(|a, b| f32::from(b.cost).total_cmp(&f32::from(a.cost)));
+                let costs: Vec<f32> = entries.iter().rev().map(|e| f32::from(e.cost)).collect();
+                let total: f32 = costs.iter().sum();
+                CategoryStats {
+                    category,
+                    total,
+                    count: costs.len(),
+                    mean: total / costs.len() as f32,
+                    median: percentile(&costs, 50.0),
+                    percentiles: PERCENTILES
+                        .iter()
+                        .map(|&p| percentile(&costs, p as f32))
+                        .collect(),
+                    largest: entries.iter().take(LARGEST).map(|&e| e.clone()).collect(),
+                    month_over_month: change(
+                        spent(category, previous_from, previous_to),
+                        spent(category, month, to),
+                    ),
+                    year_over_year: change(spent(category, year_ago(from), year_ago(to)), total),
+                    per_group: total / groups as f32,
+                }
+            })
+            .collect()
+    }
+
+    /// Where spending matching `query` in `category` is projected to land by the end of the month containing
+    /// `today`. Recurring charges that haven't come yet are added to what's been spent, and the rest of the month is
+    /// expected to go like the months before it. None if there's nothing to go on
+    pub fn forecast(
+        &self,
+        query: &Query,
+        category: Category,
+        today: NaiveDate,
+    ) -> Option<Forecast> {
+        let mut cache = self.forecasts.borrow_mut();
+        if cache.changes != self.changes {
+            cache.changes = self.changes;
+            cache.forecasts.clear();
+        }
+        let cached = cache
+            .forecasts
+            .iter()
+            .find(|(q, c, t, _)| (q, *c, *t) == (query, category, today));
+        if let Some((_, _, _, forecast)) = cached {
+            return *forecast;
+        }
+
+        let forecast = self.build_forecast(query, category, today);
+        if cache.forecasts.len() >= FORECAST_CAPACITY {
+            cache.forecasts.remove(0);
+        }
+        cache
+            .forecasts
+            .push((query.clone(), category, today, forecast));
+        forecast
+    }
+
+    fn build_forecast(
+        &self,
+        query: &Query,
+        category: Category,
+        today: NaiveDate,
+    ) -> Option<Forecast> {
+        let (start, end) = GroupBy::Month.range(GroupBy::Month.start_of(today));
+        let query = query.clone().category(category);
+        let first = GroupBy::Month.start_of(self.first_date(&query)?);
+        // the names of everything in each month, and what was last paid for them
+        let month_costs = |month: NaiveDate, to: NaiveDate| {
+            let mut costs: BTreeMap<String, f32> = BTreeMap::new();
+            for entry in self.query(&query.clone().within(month, to)).entries() {
+                costs.insert(normalize(&entry.name), f32::from(entry.cost));
             }
+            costs
+        };
+
+        // charges with the same name every month lately are expected again
+        let previous = start - Months::new(1);
+        let mut recurring = month_costs(previous, GroupBy::Month.range(previous).1);
+        for n in 2..=RECURRING_MONTHS {
+            let month = start - Months::new(n);
+            let names: BTreeSet<String> = month_costs(month, GroupBy::Month.range(month).1)
+                .into_keys()
+                .collect();
+            recurring.retain(|name, _| names.contains(name));
         }
+        let discretionary = |from: NaiveDate, to: NaiveDate| -> f32 {
+            self.query(&query.clone().within(from, to))
+                .entries()
+                .filter(|entry| !recurring.contains_key(&normalize(&entry.name)))
+                .map(|entry| f32::from(entry.cost))
+                .sum()
+        };
 
-        (earliest, latest)
+        let history: Vec<f32> = (1..=HISTORY_MONTHS)
+            .rev()
+            .map(|n| start - Months::new(n))
+            .filter(|&month| month >= first)
+            .map(|month| discretionary(month, GroupBy::Month.range(month).1))
+            .collect();
+        let (expected, spread) = predict(&history);
+
+        let this_month = month_costs(start, today);
+        let spent = self.query(&query.clone().within(start, today)).total();
+        let recurring: f32 = recurring
+            .iter()
+            .filter(|(name, _)| !this_month.contains_key(*name))
+            .map(|(_, cost)| cost)
+            .sum();
+        // the rest of the month is expected to go like the months before did, day for day
+        let days = (end - start).num_days() as f32 + 1.0;
+        let left = (end - today).num_days().max(0) as f32 / days;
+        let projected = spent + recurring + expected * left;
+        let band = spread * left.sqrt();
+        Some(Forecast {
+            category,
+            start,
+            end,
+            today,
+            spent,
+            recurring,
+            projected,
+            low: (projected - band).max(spent + recurring),
+            high: projected + band,
+        })
     }
 
-    // return a map filled with zeros for every day between the first and last entries, inclusive
-    // takes into account the grouping - entries look like:
-    // 1/1/xxxx, 2/1/xxxx, 3/1/xxxx, etc for GroupBy::Month
-    // 1/1/xxxx, 1/1/xxxx + 1, 1/1/xxxx + 2, for GroupBy::Year
-    // Assumes the entry map has something in it.
-    fn zero_cost_map(&self, group_by: GroupBy) -> CostMap {
-        let (first, last) = self.entries_date_extremes();
-        let first_days = first.unwrap().date.num_days_from_ce();
-        let last_days = last.unwrap().date.num_days_from_ce();
+    /// Entries that look like typos, double charges or fraud, and subscriptions whose charge is overdue by `today`
+    /// (see `missing_charges` for which ones, given the recurring `rules`). In date order
+    pub fn anomalies(&self, today: NaiveDate, rules: &[RecurringRule]) -> Vec<Flag> {
+        let mut flags = scan(&self.entries);
+        flags.extend(missing_charges(
+            &self.entries,
+            &self.subscriptions(),
+            rules,
+            today,
+        ));
+        flags.sort_by_key(|flag| flag.entry.date);
+        flags
+    }
+
+    /// Charges that come regularly for about the same amount, most expensive per year first
+    pub fn subscriptions(&self) -> Vec<Subscription> {
+        detect(&self.entries)
+    }
 
+    // return a map filled with zeros for every group between first and last, inclusive. Keys are the first day of
+    // each group, like 1/1/xxxx, 2/1/xxxx, 3/1/xxxx, etc for GroupBy::Month
+    // the group first falls in is included even if it starts earlier, so every entry has a key to go in
+    fn zero_cost_map(&self, group_by: &GroupBy<'_>, first: NaiveDate, last: NaiveDate) -> CostSums {
+        let dates: BTreeMap<NaiveDate, f64> = group_by
+            .starts(first, last)
+            .into_iter()
+            .map(|date| (date, 0.0))
+            .collect();
         Category::iter()
-            .map(|category| {
-                let dates = (first_days..=last_days)
-                    .filter_map(|days| {
-                        let date = NaiveDate::from_num_days_from_ce_opt(days)?;
-                        match group_by {
-                            GroupBy::Day => Some(date),
-                            GroupBy::Month if date.day() == 1 => Some(date),
-                            GroupBy::Year if date.month() == 1 && date.day() == 1 => Some(date),
-                            _ => None,
-                        }
-                    })
-                    .map(|date| (date, 0.0))
-                    .collect::<BTreeMap<NaiveDate, f32>>();
-                (category, dates)
-            })
+            .map(|category| (category, dates.clone()))
             .collect()
     }
 }
@@ -255,6 +620,7 @@ impl DataManager {
 mod tests {
     use super::*;
     use crate::category::Category;
+    use crate::entry::Cost;
 
     /// Modify the backend in place. give it a random list of (sorted) entries of a particular size
     fn _fill_entries(size: usize, backend: &mut DataManager) {
@@ -278,10 +644,11 @@ mod tests {
                 cost: Cost::try_from(rng.gen_range(1.0..=500.0)).unwrap(),
                 date,
                 category: Category::_get_random(),
+                ..Default::default()
             });
         }
 
-        backend.entries = entries;
+        backend.entries = entries.into();
         backend.sort_entries(SortBy::Date); // this shouldn't cause serialization, but if it does we need to get to
     }
 
@@ -292,12 +659,12 @@ mod tests {
         let mut backend = DataManager::default();
         tests::_fill_entries(1_000_000, &mut backend);
 
-        let (first, last) = backend.entries_date_extremes();
-        let first_days = first.unwrap().date.num_days_from_ce();
-        let last_days = last.unwrap().date.num_days_from_ce();
+        let (first, last) = backend.entries.date_range().unwrap();
+        let first_days = first.num_days_from_ce();
+        let last_days = last.num_days_from_ce();
 
         let categories = Category::_get_all();
-        let map = backend.cost_map(GroupBy::Day, categories.clone());
+        let map = backend.cost_map(CostMapView::Chart, GroupBy::Day, &Query::default());
 
         // map should have a key for every category
         assert!(categories.iter().all(|category| map.contains_key(category)));
@@ -318,5 +685,257 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_cost_map_window() {
+        let mut backend = DataManager::default();
+        for (cost, (year, month, day), category) in [
+            (10.0, (2022, 12, 24), Category::Groceries),
+            (20.0, (2023, 1, 15), Category::Groceries),
+            (30.0, (2023, 2, 15), Category::Car),
+            (40.0, (2023, 4, 2), Category::Groceries),
+        ] {
+            backend.entries.insert(Entry {
+                cost: Cost::try_from(cost).unwrap(),
+                date: NaiveDate::from_ymd_opt(year, month, day).unwrap(),
+                category,
+                ..Default::default()
+            });
+        }
+
+        let date = |year, month, day| NaiveDate::from_ymd_opt(year, month, day).unwrap();
+        // the window starts mid month, but its whole first month still gets a bar
+        let query = Query::default().between(date(2023, 1, 10), date(2023, 3, 31));
+        let map = backend.cost_map(CostMapView::Chart, GroupBy::Month, &query);
+        let months: Vec<_> = map[&Category::Car].keys().copied().collect();
+        assert_eq!(
+            months,
+            vec![date(2023, 1, 1), date(2023, 2, 1), date(2023, 3, 1)]
+        );
+        assert_eq!(map[&Category::Groceries][&date(2023, 1, 1)], 20.0);
+        assert_eq!(map[&Category::Groceries].values().sum::<f32>(), 20.0);
+    }
+
+    #[test]
+    fn test_cumulative_by_day() {
+        let mut backend = DataManager::default();
+        for (cost, (month, day), category) in [
+            (10.0, (1, 31), Category::Groceries),
+            (20.0, (2, 1), Category::Groceries),
+            (5.0, (2, 1), Category::Car),
+            (30.0, (2, 3), Category::Groceries),
+        ] {
+            backend.entries.insert(Entry {
+                cost: Cost::try_from(cost).unwrap(),
+                date: NaiveDate::from_ymd_opt(2023, month, day).unwrap(),
+                category,
+                ..Default::default()
+            });
+        }
+
+        let february = NaiveDate::from_ymd_opt(2023, 2, 1).unwrap();
+        let totals = backend
+            .cumulative_by_day(&Query::default(), &[february])
+            .remove(0);
+        assert_eq!(totals.len(), 28);
+        assert_eq!(totals[..4], [25.0, 25.0, 55.0, 55.0]);
+        assert_eq!(totals[27], 55.0);
+
+        let groceries = Query::default().category(Category::Groceries);
+        assert_eq!(
+            backend.cumulative_by_day(&groceries, &[february])[0][0],
+            20.0
+        );
+    }
+
+    #[test]
+    fn test_cached_cost_map() {
+        let mut backend = DataManager::default();
+        let entry = |cost: f32, day, category| Entry {
+            cost: Cost::try_from(cost).unwrap(),
+            date: NaiveDate::from_ymd_opt(2023, 1, day).unwrap(),
+            category,
+            ..Default::default()
+        };
+        for (cost, day, category) in [
+            (10.0, 1, Category::Groceries),
+            (20.0, 10, Category::Car),
+            (30.0, 20, Category::Groceries),
+        ] {
+            backend.add_entry(entry(cost, day, category));
+        }
+
+        let groceries = Query::default().category(Category::Groceries);
+        let queries = [Query::default(), groceries];
+        let fresh = |backend: &DataManager| {
+            queries
+                .iter()
+                .map(|query| backend.build_cost_map(&GroupBy::Day, query))
+                .collect::<Vec<_>>()
+        };
+        let cached = |backend: &DataManager| {
+            backend
+                .cost_maps(CostMapView::Chart, GroupBy::Day, &queries)
+                .into_iter()
+                .map(|map| (*map).clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(cached(&backend), fresh(&backend));
+
+        // inside the cached range, so the maps are updated in place
+        backend.add_entry(entry(5.0, 15, Category::Groceries));
+        assert_eq!(cached(&backend), fresh(&backend));
+        let key = backend.entries.iter().find(|(_, e)| e.date.day() == 10);
+        backend.remove_entry(key.unwrap().0);
+        assert_eq!(cached(&backend), fresh(&backend));
+
+        // outside of it, and at its edge, so the maps are rebuilt
+        backend.add_entry(entry(40.0, 25, Category::Car));
+        assert_eq!(cached(&backend), fresh(&backend));
+        let key = backend.entries.iter().find(|(_, e)| e.date.day() == 1);
+        backend.remove_entry(key.unwrap().0);
+        assert_eq!(cached(&backend), fresh(&backend));
+
+        // adding and removing an awkward cost over and over doesn't drift from a rebuild
+        for _ in 0..100 {
+            backend.add_entry(entry(0.1, 15, Category::Groceries));
+            let key = backend
+                .entries
+                .iter()
+                .rev()
+                .find(|(_, e)| e.date.day() == 15);
+            backend.remove_entry(key.unwrap().0);
+        }
+        assert_eq!(cached(&backend), fresh(&backend));
+
+        // a view that needs many maps doesn't push out another view's
+        let chart = backend.cost_maps(CostMapView::Chart, GroupBy::Day, &queries);
+        let months: Vec<NaiveDate> = (1..=12)
+            .map(|month| NaiveDate::from_ymd_opt(2022, month, 1).unwrap())
+            .collect();
+        backend.cumulative_by_day(&Query::default(), &months);
+        let again = backend.cost_maps(CostMapView::Chart, GroupBy::Day, &queries);
+        assert!(chart.iter().zip(&again).all(|(a, b)| Rc::ptr_eq(a, b)));
+    }
+
+    #[test]
+    fn test_statistics() {
+        let mut backend = DataManager::default();
+        for (cost, (year, month, day), category) in [
+            (10.0, (2023, 5, 1), Category::Groceries),
+            (30.0, (2023, 5, 20), Category::Groceries),
+            (20.0, (2023, 6, 2), Category::Groceries),
+            (100.0, (2023, 6, 12), Category::Rent),
+            (50.0, (2023, 5, 31), Category::Rent),
+            (25.0, (2022, 6, 3), Category::Groceries),
+        ] {
+            backend.entries.insert(Entry {
+                cost: Cost::try_from(cost).unwrap(),
+                date: NaiveDate::from_ymd_opt(year, month, day).unwrap(),
+                category,
+                ..Default::default()
+            });
+        }
+
+        let date = |month, day| NaiveDate::from_ymd_opt(2023, month, day).unwrap();
+        let query = Query::default().between(date(5, 1), date(6, 30));
+        let stats = backend.statistics(&query, &GroupBy::Month);
+        let categories: Vec<_> = stats.iter().map(|stat| stat.category).collect();
+        assert_eq!(
+            categories,
+            vec![Category::Rent, Category::Groceries, Category::All]
+        );
+
+        let groceries = &stats[1];
+        assert_eq!((groceries.total, groceries.count), (60.0, 3));
+        assert_eq!((groceries.mean, groceries.median), (20.0, 20.0));
+        assert_eq!(groceries.largest[0].date, date(5, 20));
+        assert_eq!(groceries.per_group, 30.0);
+        // june against may, and the whole period against the same one a year before
+        assert_eq!(groceries.month_over_month, Some(-0.5));
+        assert_eq!(groceries.year_over_year, Some(1.4));
+        // the whole of may counts, even its last day that june doesn't have
+        assert_eq!(stats[0].month_over_month, Some(1.0));
+        assert_eq!(stats[2].total, 210.0);
+    }
+
+    #[test]
+    fn test_forecast() {
+        let mut backend = DataManager::default();
+        for (name, cost, (month, day), category) in [
+            // the reference number banks add changes every month
+            ("NETFLIX #4921", 15.0, (1, 5), Category::Subscriptions),
+            ("NETFLIX #5037", 15.0, (2, 5), Category::Subscriptions),
+            ("NETFLIX #5160", 15.0, (3, 5), Category::Subscriptions),
+            ("Aldi", 100.0, (1, 10), Category::Groceries),
+            ("Lidl", 100.0, (2, 10), Category::Groceries),
+            ("Aldi", 100.0, (3, 10), Category::Groceries),
+            ("Costco", 30.0, (4, 2), Category::Groceries),
+        ] {
+            backend.entries.insert(Entry {
+                name: name.to_string(),
+                cost: Cost::try_from(cost).unwrap(),
+                date: NaiveDate::from_ymd_opt(2023, month, day).unwrap(),
+                category,
+                ..Default::default()
+            });
+        }
+
+        let today = NaiveDate::from_ymd_opt(2023, 4, 3).unwrap();
+        let forecast = |category| backend.forecast(&Query::default(), category, today);
+        let close = |a: f32, b: f32| (a - b).abs() < 0.01;
+
+        // netflix hasn't come yet this month, and 27 of 30 days of groceries are still to go
+        let all = forecast(Category::All).unwrap();
+        assert_eq!((all.spent, all.recurring), (30.0, 15.0));
+        assert!(close(all.projected, 135.0));
+        assert!(close(all.low, all.high));
+
+        let subscriptions = forecast(Category::Subscriptions).unwrap();
+        assert_eq!(subscriptions.projected, 15.0);
+        assert!(forecast(Category::Car).is_none());
+    }
+
+    #[test]
+    fn test_limit_status() {
+        use crate::limit::LimitPeriod;
+        let mut backend = DataManager::default();
+        for (cost, (year, month, day), category) in [
+            (10.0, (2023, 5, 1), Category::Groceries),
+            (5.5, (2023, 5, 31), Category::Groceries),
+            (100.0, (2023, 5, 12), Category::Rent),
+            (7.0, (2023, 6, 1), Category::Groceries),
+            (3.0, (2022, 5, 3), Category::Groceries),
+        ] {
+            backend.entries.insert(Entry {
+                cost: Cost::try_from(cost).unwrap(),
+                date: NaiveDate::from_ymd_opt(year, month, day).unwrap(),
+                category,
+                ..Default::default()
+            });
+        }
+
+        let date = |month, day| NaiveDate::from_ymd_opt(2023, month, day).unwrap();
+        let limit = Limit::monthly(50.0);
+        let schedule = PaySchedule::default();
+        let status = |category, limit: &Limit| {
+            backend
+                .limit_status(category, limit, date(5, 20), &schedule)
+                .unwrap()
+        };
+        assert_eq!(status(Category::Groceries, &limit).spent, 15.5);
+        assert_eq!(status(Category::All, &limit).spent, 115.5);
+        assert_eq!(status(Category::Car, &limit).spent, 0.0);
+        assert_eq!(backend.monthly_cost(Category::All, date(5, 1)), 115.5);
+
+        // a quarterly limit counts june too
+        let limit = Limit {
+            period: LimitPeriod::Quarterly,
+            ..limit
+        };
+        let quarter = status(Category::Groceries, &limit);
+        assert_eq!((quarter.start, quarter.end), (date(4, 1), date(6, 30)));
+        assert_eq!(quarter.remaining(), 27.5);
+    }
     // TODO: mock the serializer to allow testing without any actual file interaction
 }
@@ -0,0 +1,172 @@
+use crate::backend::DataManager;
+use crate::category::Category;
+use crate::organize::GroupBy;
+use chrono::{Months, NaiveDate};
+use std::collections::BTreeMap;
+
+/// Money moved from one envelope to another during a month
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq)]
+pub struct Transfer {
+    /// The first day of the month the money was moved in
+    pub month: NaiveDate,
+    pub from: Category,
+    pub to: Category,
+    pub amount: f32,
+}
+
+/// One category's envelope for one month
+#[derive(Debug, Clone, PartialEq)]
+pub struct Envelope {
+    pub category: Category,
+    /// What was left over (or overspent, if negative) at the end of last month
+    pub carried: f32,
+    pub allocated: f32,
+    /// Net amount moved in from other envelopes
+    pub moved: f32,
+    pub spent: f32,
+}
+
+impl Envelope {
+    /// Negative once the envelope is overspent. Whatever is left rolls over into next month
+    pub fn available(&self) -> f32 {
+        self.carried + self.allocated + self.moved - self.spent
+    }
+}
+
+/// Envelope budgeting: each category gets an allocation every month, and whatever isn't spent (or is overspent)
+/// rolls over into the next one
+#[derive(serde::Deserialize, serde::Serialize, Clone)]
+#[serde(default)] // if we add new fields, give them default values when deserializing old state
+pub struct EnvelopeBudget {
+    /// Envelopes are tracked from the first of this month. Nothing before it rolls over
+    pub start: NaiveDate,
+    pub transfers: Vec<Transfer>,
+}
+
+impl Default for EnvelopeBudget {
+    fn default() -> Self {
+        Self {
+            start: GroupBy::Month.start_of(chrono::Local::now().date_naive()),
+            transfers: vec![],
+        }
+    }
+}
+
+impl EnvelopeBudget {
+    /// The envelope of every category in `allocations` for the month containing `date`, with everything since
+    /// `start` rolled over into it
+    pub fn envelopes(
+        &self,
+        allocations: &BTreeMap<Category, f32>,
+        backend: &DataManager,
+        date: NaiveDate,
+    ) -> Vec<Envelope> {
+        let target = GroupBy::Month.start_of(date);
+        let mut carried: BTreeMap<Category, f32> = BTreeMap::new();
+        let mut month = self.start.min(target);
+        loop {
+            let envelopes: Vec<Envelope> = allocations
+                .iter()
+                .map(|(&category, &allocated)| Envelope {
+                    category,
+                    carried: carried.get(&category).copied().unwrap_or_default(),
+                    allocated,
+                    moved: self.moved(category, month),
+                    spent: backend.monthly_cost(category, month),
+                })
+                .collect();
+
+            if month >= target {
+                return envelopes;
+            }
+            carried = envelopes
+                .iter()
+                .map(|envelope| (envelope.category, envelope.available()))
+                .collect();
+            month = month + Months::new(1);
+        }
+    }
+
+    /// The net amount moved into `category`'s envelope during `month`
+    fn moved(&self, category: Category, month: NaiveDate) -> f32 {
+        self.transfers
+            .iter()
+            .filter(|transfer| transfer.month == month)
+            .map(|transfer| {
+                if transfer.to == category {
+                    transfer.amount
+                } else if transfer.from == category {
+                    -transfer.amount
+                } else {
+                    0.0
+                }
+            })
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entry::{Cost, Entry};
+
+    fn date(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2023, month, day).unwrap()
+    }
+
+    #[test]
+    fn test_rollover() {
+        let mut backend = DataManager::default();
+        for (cost, (month, day), category) in [
+            (80.0, (1, 5), Category::Groceries),
+            (130.0, (2, 10), Category::Groceries),
+            (20.0, (2, 11), Category::Clothes),
+        ] {
+            backend.entries.insert(Entry {
+                cost: Cost::try_from(cost).unwrap(),
+                date: date(month, day),
+                category,
+                ..Default::default()
+            });
+        }
+
+        let allocations = BTreeMap::from([(Category::Groceries, 100.0), (Category::Clothes, 50.0)]);
+        let mut budget = EnvelopeBudget {
+            start: date(1, 1),
+            transfers: vec![],
+        };
+        let available = |budget: &EnvelopeBudget, month| {
+            budget
+                .envelopes(&allocations, &backend, date(month, 15))
+                .iter()
+                .map(|envelope| (envelope.category, envelope.available()))
+                .collect::<Vec<_>>()
+        };
+
+        // ordered like Category, so clothes sort after groceries
+        assert_eq!(
+            available(&budget, 1),
+            vec![(Category::Groceries, 20.0), (Category::Clothes, 50.0)]
+        );
+        // january's surplus covers part of february's overspending, and the rest carries into march
+        assert_eq!(
+            available(&budget, 2),
+            vec![(Category::Groceries, -10.0), (Category::Clothes, 80.0)]
+        );
+        assert_eq!(
+            available(&budget, 3),
+            vec![(Category::Groceries, 90.0), (Category::Clothes, 130.0)]
+        );
+
+        budget.transfers.push(Transfer {
+            month: date(2, 1),
+            from: Category::Clothes,
+            to: Category::Groceries,
+            amount: 10.0,
+        });
+        assert_eq!(
+            available(&budget, 3),
+            vec![(Category::Groceries, 100.0), (Category::Clothes, 120.0)]
+        );
+    }
+}
@@ -0,0 +1,135 @@
+use crate::backend::CostMap;
+use crate::category::Category;
+use crate::entry::Entry;
+use crate::organize::GroupBy;
+use crate::query::Query;
+use chrono::NaiveDate;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+/// A cost map's totals, kept in f64 so adding and removing entries over and over doesn't drift from a rebuild
+pub type CostSums = BTreeMap<Category, BTreeMap<NaiveDate, f64>>;
+
+/// The views that show cost maps. Each one keeps its own maps, so a view that needs many of them at once (like a
+/// pace chart averaging two years of months) can't push out the ones another view needs every frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CostMapView {
+    Chart,
+    Calendar,
+    Comparison,
+    Pace,
+}
+
+/// A cost map that was already built, along with what it was built for
+struct CachedCostMap {
+    group_by: GroupBy<'static>,
+    query: Query,
+    sums: CostSums,
+    map: Rc<CostMap>,
+}
+
+/// Cost maps that were already built, so they don't have to be rebuilt from every entry each frame. Adding or
+/// removing an entry updates the maps it's counted in. Whatever can't be updated in place (like an entry that
+/// stretches the range a map covers) is dropped and rebuilt the next time it's asked for
+#[derive(Default)]
+pub struct CostMapCache {
+    // the maps each view asked for last time, and nothing else
+    views: BTreeMap<CostMapView, Vec<CachedCostMap>>,
+}
+
+impl CostMapCache {
+    /// The maps `view` needs, one per query. Ones it had before are reused and the rest are built with `build`.
+    /// Anything else the view had is dropped, since it moved on from it
+    pub fn get_or_build(
+        &mut self,
+        view: CostMapView,
+        group_by: &GroupBy<'_>,
+        queries: &[Query],
+        mut build: impl FnMut(&Query) -> CostSums,
+    ) -> Vec<Rc<CostMap>> {
+        let mut old = self.views.remove(&view).unwrap_or_default();
+        let cached: Vec<CachedCostMap> = queries
+            .iter()
+            .map(|query| {
+                let found = old
+                    .iter()
+                    .position(|cached| cached.group_by == *group_by && cached.query == *query);
+                match found {
+                    Some(index) => old.swap_remove(index),
+                    None => {
+                        let sums = build(query);
+                        CachedCostMap {
+                            group_by: group_by.clone().into_owned(),
+                            query: query.clone(),
+                            map: Rc::new(to_cost_map(&sums)),
+                            sums,
+                        }
+                    }
+                }
+            })
+            .collect();
+        let maps = cached.iter().map(|cached| Rc::clone(&cached.map)).collect();
+        self.views.insert(view, cached);
+        maps
+    }
+
+    pub fn clear(&mut self) {
+        self.views.clear();
+    }
+
+    /// Count a new entry in every map it belongs in
+    pub fn add_entry(&mut self, entry: &Entry) {
+        self.update(entry, 1.0, false);
+    }
+
+    /// Stop counting a removed entry. Maps it was at the edge of are dropped, since their range might shrink
+    pub fn remove_entry(&mut self, entry: &Entry) {
+        self.update(entry, -1.0, true);
+    }
+
+    fn update(&mut self, entry: &Entry, sign: f64, drop_edges: bool) {
+        for maps in self.views.values_mut() {
+            maps.retain_mut(|cached| {
+                let bucket = cached.group_by.start_of(entry.date);
+                let Some(dates) = cached.sums.values().next() else {
+                    return false;
+                };
+                // the map only covers the range it was built for
+                if !dates.contains_key(&bucket) {
+                    return false;
+                }
+                let at_edge = dates.keys().next() == Some(&bucket)
+                    || dates.keys().next_back() == Some(&bucket);
+                if drop_edges && at_edge {
+                    return false;
+                }
+                if cached.query.matcher()(entry) {
+                    let sum = cached
+                        .sums
+                        .entry(entry.category)
+                        .or_default()
+                        .entry(bucket)
+                        .or_default();
+                    *sum += sign * f32::from(entry.cost) as f64;
+                    let sum = *sum as f32;
+                    let map = Rc::make_mut(&mut cached.map);
+                    map.entry(entry.category).or_default().insert(bucket, sum);
+                }
+                true
+            });
+        }
+    }
+}
+
+/// The cost map `sums` adds up to
+pub fn to_cost_map(sums: &CostSums) -> CostMap {
+    sums.iter()
+        .map(|(category, dates)| {
+            let dates = dates
+                .iter()
+                .map(|(date, sum)| (*date, *sum as f32))
+                .collect();
+            (*category, dates)
+        })
+        .collect()
+}
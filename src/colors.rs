@@ -1,7 +1,7 @@
 use egui::Color32;
 use strum_macros::EnumIter;
 
-#[derive(Debug, EnumIter, Clone, Copy, PartialEq, Eq)]
+#[derive(serde::Deserialize, serde::Serialize, Debug, EnumIter, Clone, Copy, PartialEq, Eq)]
 pub enum Theme {
     Sunset,
     Desert,
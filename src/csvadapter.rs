@@ -1,26 +1,70 @@
+use crate::csvformat::CsvFormat;
 use crate::entry::Entry;
 
-use csv::ReaderBuilder;
+use chrono::Datelike;
+use csv::{ReaderBuilder, StringRecord, WriterBuilder};
+use std::collections::{BTreeMap, BTreeSet};
 use std::error::Error;
 use std::fs::File;
 use std::io::{Read, Result as IoResult, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-pub fn write_entries_to_csv(entries: &[Entry], file_path: &PathBuf) -> IoResult<()> {
+/// The layout version new csv files are written with. Older layouts are migrated when they're read
+///
+/// 1. No marker or header, every row is `name,date,cost,category`
+/// 2. A `#pennypilot-csv v2` marker line, then a header row. Columns are found by name, so they can be in any
+///    order, and columns PennyPilot doesn't know about are kept when the file is written back
+pub const CSV_VERSION: u32 = 2;
+
+/// The first line of every csv file from version 2 on starts with this, followed by the version
+const VERSION_MARKER: &str = "#pennypilot-csv v";
+
+/// The columns every entry has, in the order they're written
+const COLUMNS: [&str; 4] = ["name", "date", "cost", "category"];
+
+/// `MIGRATIONS[n]` upgrades a table from version n + 1 to n + 2. Add one whenever `CSV_VERSION` is bumped
+const MIGRATIONS: [fn(&mut Table); (CSV_VERSION - 1) as usize] = [migrate_v1_to_v2];
+
+/// The rows of a csv file along with the names of its columns
+struct Table {
+    header: Vec<String>,
+    rows: Vec<StringRecord>,
+}
+
+/// Version 1 files have no header, but their columns were always in the same order
+fn migrate_v1_to_v2(table: &mut Table) {
+    table.header = COLUMNS.iter().map(|column| column.to_string()).collect();
+}
+
+/// The contents of a csv file, plus how it was laid out
+pub struct CsvContents {
+    pub entries: Vec<Entry>,
+    pub format: CsvFormat,
+    /// the layout version the file was written with. Writing with the same version keeps it readable by the
+    /// builds that wrote it
+    pub version: u32,
+}
+
+pub fn write_entries_to_csv<'a>(
+    entries: impl IntoIterator<Item = &'a Entry> + Clone,
+    file_path: &PathBuf,
+    format: &CsvFormat,
+    version: u32,
+) -> IoResult<()> {
     let backup_path = file_path.with_extension("backup");
     File::create(&backup_path)?;
-    let dest_path = file_path.clone();
-    // dest_path.set_file_name(backup_path);
-    std::fs::copy(file_path, dest_path)?;
+    // a year file in a ledger directory might not exist yet, so there's nothing to back up
+    if file_path.exists() {
+        let dest_path = file_path.clone();
+        // dest_path.set_file_name(backup_path);
+        std::fs::copy(file_path, dest_path)?;
+    }
 
     // opens the file if it exists and clears its contents, which is why we create a backup
-    let mut file = File::create(file_path)?;
+    let file = File::create(file_path)?;
 
-    debug!("Writing entries");
-    for entry in entries {
-        let csv_string = entry.to_csv_string();
-        writeln!(file, "{}", csv_string)?;
-    }
+    debug!("Writing entries (v{}, {})", version, format);
+    write_entries_to_writer(entries, file, format, version)?;
 
     if backup_path.exists() {
         // everything worked, remove the backup file now
@@ -31,27 +75,572 @@ pub fn write_entries_to_csv(entries: &[Entry], file_path: &PathBuf) -> IoResult<
     Ok(())
 }
 
-/// Read entries from something implementing the `Read` trait
-fn read_entries_from_reader<R: Read>(reader: R) -> Result<Vec<Entry>, Box<dyn Error>> {
-    let mut rdr = ReaderBuilder::new().has_headers(false).from_reader(reader);
+/// Write entries to something implementing the `Write` trait, laid out as layout `version`.
+/// Versions newer than `CSV_VERSION` are written like the current one, with their extra columns kept
+pub fn write_entries_to_writer<'a, W: Write>(
+    entries: impl IntoIterator<Item = &'a Entry> + Clone,
+    mut writer: W,
+    format: &CsvFormat,
+    version: u32,
+) -> IoResult<()> {
+    if version >= 2 {
+        writeln!(writer, "{}{}", VERSION_MARKER, version)?;
+    }
+
+    let mut wtr = WriterBuilder::new()
+        .has_headers(false)
+        .delimiter(format.delimiter.byte())
+        .from_writer(writer);
+
+    // every column any entry has, so that nothing read from the file is lost
+    let mut extra_columns: Vec<&String> = entries
+        .clone()
+        .into_iter()
+        .flat_map(|e| e.extra.keys())
+        .collect();
+    extra_columns.sort();
+    extra_columns.dedup();
+    if version == 1 && !extra_columns.is_empty() {
+        warn!(
+            "Version 1 csv files can't hold extra columns, dropping {:?}",
+            extra_columns
+        );
+        extra_columns.clear();
+    }
+
+    if version >= 2 {
+        let mut header: Vec<&str> = COLUMNS.to_vec();
+        header.extend(extra_columns.iter().map(|column| column.as_str()));
+        wtr.write_record(&header)?;
+    }
+
+    for entry in entries {
+        let mut record = entry.to_record(format);
+        for column in &extra_columns {
+            record.push_field(entry.extra.get(*column).map_or("", |value| value.as_str()));
+        }
+        wtr.write_record(&record)?;
+    }
+    wtr.flush()
+}
+
+/// Split off the version marker, if there is one. Files without one are version 1
+fn split_version(data: &[u8]) -> Result<(u32, &[u8]), Box<dyn Error>> {
+    let Some(rest) = data.strip_prefix(VERSION_MARKER.as_bytes()) else {
+        return Ok((1, data));
+    };
+    let line_end = rest.iter().position(|&b| b == b'\n').unwrap_or(rest.len());
+    let version = std::str::from_utf8(&rest[..line_end])?
+        .trim()
+        .parse::<u32>()?;
+    if version == 0 {
+        return Err("Invalid csv version 0".into());
+    }
+    Ok((version, rest.get(line_end + 1..).unwrap_or_default()))
+}
+
+/// Read entries from something implementing the `Read` trait. Files with an older layout are migrated to the
+/// current one; newer files are read as well as possible by column name.
+/// If `format` is None, it's detected from the data. Either way, the format that was used is returned
+fn read_entries_from_reader<R: Read>(
+    mut reader: R,
+    format: Option<CsvFormat>,
+) -> Result<CsvContents, Box<dyn Error>> {
+    let mut data = vec![];
+    reader.read_to_end(&mut data)?;
+    let (version, body) = split_version(&data)?;
+    if version > CSV_VERSION {
+        warn!(
+            "File is csv version {}, newer than this build knows ({}). Reading columns by name",
+            version, CSV_VERSION
+        );
+    }
+    let has_header = version >= 2;
+    let format = format.unwrap_or_else(|| CsvFormat::detect(body, has_header));
+
+    let mut rows = ReaderBuilder::new()
+        .has_headers(false)
+        .delimiter(format.delimiter.byte())
+        .from_reader(body)
+        .into_records()
+        .filter_map(|result| result.ok());
+    let header = match has_header {
+        true => rows
+            .next()
+            .map(|header| header.iter().map(|c| c.trim().to_string()).collect()),
+        false => None,
+    };
+    let mut table = Table {
+        header: header.unwrap_or_default(),
+        rows: rows.collect(),
+    };
+
+    for migration in MIGRATIONS.iter().skip(version as usize - 1) {
+        migration(&mut table);
+    }
+
+    // where each known column is, and the name and position of everything else
+    let known: Vec<usize> = COLUMNS
+        .iter()
+        .map(|column| {
+            table
+                .header
+                .iter()
+                .position(|name| name == column)
+                .ok_or_else(|| format!("Missing column \"{}\"", column))
+        })
+        .collect::<Result<_, _>>()?;
+    let extra: Vec<(usize, &String)> = table
+        .header
+        .iter()
+        .enumerate()
+        .filter(|(idx, _)| !known.contains(idx))
+        .collect();
 
-    rdr.records()
-        .filter_map(|result| result.ok())
-        // Entry implements try_from<StringRecord> to make this simple for us
-        .map(Entry::try_from)
-        .collect()
+    let entries = table
+        .rows
+        .iter()
+        .map(|row| {
+            if row.len() != table.header.len() {
+                return Err(
+                    format!("Record must have exactly {} fields", table.header.len()).into(),
+                );
+            }
+            let record: StringRecord = known.iter().map(|&idx| &row[idx]).collect();
+            let mut entry = Entry::from_record(&record, &format)?;
+            entry.extra = extra
+                .iter()
+                .map(|(idx, name)| (name.to_string(), row[*idx].to_string()))
+                .collect();
+            Ok(entry)
+        })
+        .collect::<Result<_, Box<dyn Error>>>()?;
+
+    Ok(CsvContents {
+        entries,
+        format,
+        version,
+    })
 }
 
 /// Read a vector of `Entry`s from a csv file at `file_path`
-pub fn read_entries_from_file(file_path: &PathBuf) -> Result<Vec<Entry>, Box<dyn Error>> {
+pub fn read_entries_from_file(
+    file_path: &PathBuf,
+    format: Option<CsvFormat>,
+) -> Result<CsvContents, Box<dyn Error>> {
     let file = File::open(file_path)?;
-    read_entries_from_reader(file)
+    read_entries_from_reader(file, format)
 }
 
 #[cfg(target_arch = "wasm32")]
 /// Read a vector of `Entry`s from a byte vector
-pub fn read_entries_from_vec(buffer: Vec<u8>) -> Result<Vec<Entry>, Box<dyn Error>> {
+pub fn read_entries_from_vec(
+    buffer: Vec<u8>,
+    format: Option<CsvFormat>,
+) -> Result<CsvContents, Box<dyn Error>> {
     use std::io::Cursor;
     let cursor = Cursor::new(buffer);
-    read_entries_from_reader(cursor)
+    read_entries_from_reader(cursor, format)
+}
+
+/// A ledger that's split into one csv file per year (2021.csv, 2022.csv, ...) but treated as a single dataset.
+///
+/// `pattern` is either a directory (every csv file in it is part of the ledger) or a path whose file name has a
+/// single `*` wildcard, like `ledgers/budget-*.csv`. Every file needs a 4 digit year in its name; that's what
+/// entries get routed back to when they're written.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
+pub struct YearLedger {
+    pub pattern: PathBuf,
+
+    #[serde(skip)]
+    // the file backing each year, discovered when the ledger is opened
+    files: BTreeMap<i32, PathBuf>,
+
+    #[serde(skip)]
+    // years whose file has to be rewritten because an entry was read from the wrong one: both the file it was in
+    // and the one it belongs in. They're written along with the next year that is
+    misplaced: BTreeSet<i32>,
+}
+
+impl YearLedger {
+    #[cfg(not(target_arch = "wasm32"))]
+    /// Find every year file matching `pattern` and read all of their entries. `format` works like it does for
+    /// `read_entries_from_file`; the format of the first year is the one returned, along with the oldest layout
+    /// version so that writing never makes a file unreadable to the build that wrote it
+    pub fn open(
+        pattern: PathBuf,
+        format: Option<CsvFormat>,
+    ) -> Result<(Self, CsvContents), Box<dyn Error>> {
+        let mut ledger = YearLedger {
+            pattern,
+            files: BTreeMap::new(),
+            misplaced: BTreeSet::new(),
+        };
+        ledger.files = ledger.find_year_files()?;
+
+        let mut contents = CsvContents {
+            entries: vec![],
+            format: format.unwrap_or_default(),
+            version: CSV_VERSION,
+        };
+        for (idx, (year, path)) in ledger.files.iter().enumerate() {
+            let year_contents = read_entries_from_file(path, format)?;
+            if idx == 0 {
+                contents.format = year_contents.format;
+            }
+            contents.version = contents.version.min(year_contents.version);
+
+            for entry in year_contents
+                .entries
+                .iter()
+                .filter(|e| e.date.year() != *year)
+            {
+                // still load it - it'll be moved to the right file the next time anything is written
+                warn!("{:?} contains an entry from another year: {}", path, entry);
+                ledger.misplaced.extend([*year, entry.date.year()]);
+            }
+            contents.entries.extend(year_contents.entries);
+        }
+
+        Ok((ledger, contents))
+    }
+
+    /// Write the entries dated in `year` to that year's file, creating it if needed. Other entries are ignored,
+    /// unless they were read from the wrong year's file; then both that file and theirs are written too, so they're
+    /// moved rather than dropped
+    pub fn write_year<'a>(
+        &mut self,
+        entries: impl IntoIterator<Item = &'a Entry> + Clone,
+        year: i32,
+        format: &CsvFormat,
+        version: u32,
+    ) -> IoResult<()> {
+        while let Some(misplaced) = self.misplaced.first().copied() {
+            self.write_one_year(entries.clone(), misplaced, format, version)?;
+            self.misplaced.remove(&misplaced);
+        }
+        self.write_one_year(entries, year, format, version)
+    }
+
+    fn write_one_year<'a>(
+        &mut self,
+        entries: impl IntoIterator<Item = &'a Entry>,
+        year: i32,
+        format: &CsvFormat,
+        version: u32,
+    ) -> IoResult<()> {
+        let path = self.file_for_year(year);
+        let year_entries: Vec<&Entry> = entries
+            .into_iter()
+            .filter(|entry| entry.date.year() == year)
+            .collect();
+        write_entries_to_csv(year_entries.iter().copied(), &path, format, version)
+    }
+
+    /// Write every year, including the ones that no longer have any entries so deletions are saved too
+    pub fn write_all<'a>(
+        &mut self,
+        entries: impl IntoIterator<Item = &'a Entry> + Clone,
+        format: &CsvFormat,
+        version: u32,
+    ) -> IoResult<()> {
+        let mut years: Vec<i32> = self.files.keys().copied().collect();
+        years.extend(entries.clone().into_iter().map(|entry| entry.date.year()));
+        years.sort_unstable();
+        years.dedup();
+
+        for year in years {
+            self.write_year(entries.clone(), year, format, version)?;
+        }
+        Ok(())
+    }
+
+    /// The file that holds entries for `year`. Years without a file get one named like the existing files
+    /// (budget-2021.csv -> budget-2024.csv), or `<year>.csv` for an empty ledger
+    pub fn file_for_year(&mut self, year: i32) -> PathBuf {
+        let (dir, prefix, suffix) = self.split_pattern();
+        let template = self.files.values().next().and_then(|path| {
+            let name = path.file_name()?.to_str()?;
+            let (start, _) = find_year(name)?;
+            Some((name[..start].to_string(), name[start + 4..].to_string()))
+        });
+        let (prefix, suffix) = template.unwrap_or((prefix, suffix));
+
+        self.files
+            .entry(year)
+            .or_insert_with(|| dir.join(format!("{}{}{}", prefix, year, suffix)))
+            .clone()
+    }
+
+    /// Split the pattern into the directory to search and the prefix/suffix around the wildcard
+    fn split_pattern(&self) -> (PathBuf, String, String) {
+        if self.pattern.is_dir() {
+            return (self.pattern.clone(), String::new(), ".csv".to_string());
+        }
+
+        let dir = self
+            .pattern
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        let name = self
+            .pattern
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        match name.split_once('*') {
+            Some((prefix, suffix)) => (dir, prefix.to_string(), suffix.to_string()),
+            // not a glob, so the pattern is a directory that doesn't exist yet
+            None => (self.pattern.clone(), String::new(), ".csv".to_string()),
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn find_year_files(&self) -> Result<BTreeMap<i32, PathBuf>, Box<dyn Error>> {
+        let (dir, prefix, suffix) = self.split_pattern();
+
+        let mut files = BTreeMap::new();
+        for dir_entry in std::fs::read_dir(dir)? {
+            let path = dir_entry?.path();
+            let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            if !path.is_file()
+                || name.len() < prefix.len() + suffix.len()
+                || !name.starts_with(&prefix)
+                || !name.ends_with(&suffix)
+            {
+                continue;
+            }
+
+            match find_year(name) {
+                Some((_, year)) => {
+                    if let Some(other) = files.insert(year, path.clone()) {
+                        return Err(format!(
+                            "Both {:?} and {:?} hold entries for {}",
+                            other, path, year
+                        )
+                        .into());
+                    }
+                }
+                None => warn!("Skipping {:?}: no year in its name", path),
+            }
+        }
+
+        Ok(files)
+    }
+}
+
+/// Find the first run of exactly 4 digits in `name`, returning its byte offset and value
+fn find_year(name: &str) -> Option<(usize, i32)> {
+    let bytes = name.as_bytes();
+    let mut start = 0;
+    while start < bytes.len() {
+        if !bytes[start].is_ascii_digit() {
+            start += 1;
+            continue;
+        }
+        let end = start
+            + bytes[start..]
+                .iter()
+                .take_while(|b| b.is_ascii_digit())
+                .count();
+        if end - start == 4 {
+            return Some((start, name[start..end].parse().ok()?));
+        }
+        start = end;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::category::Category;
+    use crate::entry::Cost;
+    use chrono::NaiveDate;
+
+    fn entry(name: &str, date: (i32, u32, u32)) -> Entry {
+        Entry {
+            name: name.to_string(),
+            cost: Cost::try_from(10.0).unwrap(),
+            date: NaiveDate::from_ymd_opt(date.0, date.1, date.2).unwrap(),
+            category: Category::Groceries,
+            ..Default::default()
+        }
+    }
+
+    /// A fresh, empty directory for a test to write csv files into
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("penny_pilot_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_find_year() {
+        assert_eq!(find_year("2021.csv"), Some((0, 2021)));
+        assert_eq!(find_year("budget-2022.csv"), Some((7, 2022)));
+        assert_eq!(find_year("v12-2023.csv"), Some((4, 2023)));
+        assert_eq!(find_year("123456.csv"), None);
+        assert_eq!(find_year("notes.csv"), None);
+    }
+
+    #[test]
+    fn test_year_ledger_round_trip() {
+        let dir = test_dir("year_ledger");
+        let format = CsvFormat::default();
+        for (name, entry) in [
+            ("budget-2021.csv", entry("a", (2021, 3, 1))),
+            ("budget-2022.csv", entry("b", (2022, 5, 9))),
+            ("other-2020.csv", entry("c", (2020, 1, 1))),
+        ] {
+            write_entries_to_csv(&[entry], &dir.join(name), &format, CSV_VERSION).unwrap();
+        }
+
+        let (mut ledger, contents) = YearLedger::open(dir.join("budget-*.csv"), None).unwrap();
+        let mut entries = contents.entries;
+        assert_eq!(
+            entries,
+            vec![entry("a", (2021, 3, 1)), entry("b", (2022, 5, 9))]
+        );
+
+        // a new year gets a file named like the others
+        entries.push(entry("d", (2024, 2, 2)));
+        ledger
+            .write_year(&entries, 2024, &format, CSV_VERSION)
+            .unwrap();
+        let written = read_entries_from_file(&dir.join("budget-2024.csv"), None).unwrap();
+        assert_eq!(written.entries, vec![entry("d", (2024, 2, 2))]);
+
+        // only the edited year's file changes
+        entries.push(entry("e", (2021, 12, 31)));
+        ledger
+            .write_year(&entries, 2021, &format, CSV_VERSION)
+            .unwrap();
+        let (_, reloaded) = YearLedger::open(dir.join("budget-*.csv"), None).unwrap();
+        assert_eq!(reloaded.entries.len(), 4);
+
+        // a plain directory picks up every csv file
+        let (_, everything) = YearLedger::open(dir.clone(), None).unwrap();
+        assert_eq!(everything.entries.len(), 5);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_misplaced_entry() {
+        let dir = test_dir("misplaced");
+        let format = CsvFormat::default();
+        let misplaced = entry("late", (2022, 1, 3));
+        write_entries_to_csv(
+            &[entry("a", (2021, 3, 1)), misplaced.clone()],
+            &dir.join("2021.csv"),
+            &format,
+            CSV_VERSION,
+        )
+        .unwrap();
+        write_entries_to_csv(
+            &[entry("b", (2023, 5, 9))],
+            &dir.join("2023.csv"),
+            &format,
+            CSV_VERSION,
+        )
+        .unwrap();
+
+        // saving any year moves the entry into its own year's file, rather than dropping it
+        let (mut ledger, contents) = YearLedger::open(dir.clone(), None).unwrap();
+        ledger
+            .write_year(&contents.entries, 2023, &format, CSV_VERSION)
+            .unwrap();
+        let (_, reloaded) = YearLedger::open(dir.clone(), None).unwrap();
+        assert_eq!(reloaded.entries.len(), 3);
+        let moved = read_entries_from_file(&dir.join("2022.csv"), None).unwrap();
+        assert_eq!(moved.entries, vec![misplaced]);
+        let source = read_entries_from_file(&dir.join("2021.csv"), None).unwrap();
+        assert_eq!(source.entries, vec![entry("a", (2021, 3, 1))]);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_european_round_trip() {
+        use crate::csvformat::{DateFormat, Delimiter, NumberFormat};
+        let format = CsvFormat {
+            date: DateFormat::DayMonthYearDot,
+            number: NumberFormat::DecimalComma,
+            delimiter: Delimiter::Semicolon,
+        };
+        let mut entries = vec![entry("Bäckerei; Markt", (2023, 11, 4))];
+        entries[0].cost = Cost::try_from(1234.5).unwrap();
+
+        let mut buf = vec![];
+        write_entries_to_writer(&entries, &mut buf, &format, CSV_VERSION).unwrap();
+        assert_eq!(
+            String::from_utf8(buf.clone()).unwrap(),
+            "#pennypilot-csv v2\nname;date;cost;category\n\"Bäckerei; Markt\";04.11.2023;1234,5;Groceries\n"
+        );
+
+        let read = read_entries_from_reader(buf.as_slice(), None).unwrap();
+        assert_eq!(read.format, format);
+        assert_eq!(read.entries, entries);
+        assert_eq!(read.entries[0].cost, entries[0].cost);
+    }
+
+    /// Write `entries` to a string with the default format
+    fn write_string(entries: &[Entry], version: u32) -> String {
+        let mut buf = vec![];
+        write_entries_to_writer(entries, &mut buf, &CsvFormat::default(), version).unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+
+    #[test]
+    fn test_version_1() {
+        let v1 = "Bread,2023-01-02,3.5,Groceries\nGas,2023-01-03,40.0,Car\n";
+        let read = read_entries_from_reader(v1.as_bytes(), None).unwrap();
+        assert_eq!(read.version, 1);
+        assert_eq!(read.entries.len(), 2);
+        assert_eq!(read.entries[1].category, Category::Car);
+        assert!(read.entries.iter().all(|entry| entry.extra.is_empty()));
+
+        // written back the same way, so builds from before versioning can still read it
+        assert_eq!(write_string(&read.entries, 1), v1);
+
+        // upgrading adds the marker and header
+        let upgraded = write_string(&read.entries, CSV_VERSION);
+        assert!(upgraded.starts_with("#pennypilot-csv v2\nname,date,cost,category\n"));
+
+        // a wrong number of fields is still an error
+        assert!(read_entries_from_reader("a,2023-01-02,1.0\n".as_bytes(), None).is_err());
+    }
+
+    #[test]
+    fn test_version_2() {
+        let v2 = "#pennypilot-csv v2\ncategory,notes,name,cost,date\nRent,paid late,January,1200.0,2023-01-01\n";
+        let read = read_entries_from_reader(v2.as_bytes(), None).unwrap();
+        assert_eq!(read.version, 2);
+        assert_eq!(read.entries[0].name, "January");
+        assert_eq!(read.entries[0].cost, Cost::try_from(1200.0).unwrap());
+        assert_eq!(read.entries[0].extra["notes"], "paid late");
+
+        // unknown columns survive a round trip
+        assert_eq!(
+            write_string(&read.entries, 2),
+            "#pennypilot-csv v2\nname,date,cost,category,notes\nJanuary,2023-01-01,1200.0,Rent,paid late\n"
+        );
+
+        let missing_cost = "#pennypilot-csv v2\nname,date,category\nJanuary,2023-01-01,Rent\n";
+        assert!(read_entries_from_reader(missing_cost.as_bytes(), None).is_err());
+    }
+
+    #[test]
+    fn test_newer_version() {
+        // a file from a future build is read by column name and keeps its version
+        let v9 = "#pennypilot-csv v9\nid,name,date,cost,category\n7,Movie,2023-05-05,12.0,Misc\n";
+        let read = read_entries_from_reader(v9.as_bytes(), None).unwrap();
+        assert_eq!(read.version, 9);
+        assert_eq!(read.entries[0].extra["id"], "7");
+        assert!(write_string(&read.entries, read.version).contains("Movie,2023-05-05,12.0,Misc,7"));
+    }
 }
@@ -0,0 +1,281 @@
+use csv::ReaderBuilder;
+use std::num::ParseFloatError;
+use strum_macros::EnumIter;
+
+/// How many rows of a file are looked at to detect its format
+const SAMPLE_ROWS: usize = 50;
+
+/// How dates are written in a csv file
+#[derive(serde::Deserialize, serde::Serialize, EnumIter, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateFormat {
+    YearMonthDay,
+    DayMonthYearDot,
+    DayMonthYearSlash,
+    MonthDayYearSlash,
+}
+
+impl std::fmt::Display for DateFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            DateFormat::YearMonthDay => write!(f, "2024-01-31"),
+            DateFormat::DayMonthYearDot => write!(f, "31.01.2024"),
+            DateFormat::DayMonthYearSlash => write!(f, "31/01/2024"),
+            DateFormat::MonthDayYearSlash => write!(f, "01/31/2024"),
+        }
+    }
+}
+
+impl DateFormat {
+    /// The chrono format string for parsing and formatting
+    pub fn pattern(&self) -> &'static str {
+        match *self {
+            DateFormat::YearMonthDay => "%Y-%m-%d",
+            DateFormat::DayMonthYearDot => "%d.%m.%Y",
+            DateFormat::DayMonthYearSlash => "%d/%m/%Y",
+            DateFormat::MonthDayYearSlash => "%m/%d/%Y",
+        }
+    }
+}
+
+/// Which character separates the decimal part of a cost. The other one is accepted as a thousands separator
+#[derive(serde::Deserialize, serde::Serialize, EnumIter, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumberFormat {
+    /// 1,234.56
+    DecimalPoint,
+    /// 1.234,56
+    DecimalComma,
+}
+
+impl std::fmt::Display for NumberFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            NumberFormat::DecimalPoint => write!(f, "1,234.56"),
+            NumberFormat::DecimalComma => write!(f, "1.234,56"),
+        }
+    }
+}
+
+impl NumberFormat {
+    pub fn parse(&self, s: &str) -> Result<f32, ParseFloatError> {
+        let normalized: String = match *self {
+            NumberFormat::DecimalPoint => s.trim().chars().filter(|&c| c != ',').collect(),
+            NumberFormat::DecimalComma => s
+                .trim()
+                .chars()
+                .filter(|&c| c != '.')
+                .map(|c| if c == ',' { '.' } else { c })
+                .collect(),
+        };
+        normalized.parse()
+    }
+
+    /// Thousands separators are never written so the value reads back the same way
+    pub fn format(&self, value: f32) -> String {
+        match *self {
+            NumberFormat::DecimalPoint => format!("{:?}", value),
+            NumberFormat::DecimalComma => format!("{:?}", value).replace('.', ","),
+        }
+    }
+
+    /// Guess which format a single number is written in. None if it could be either (`12`, `1.234`, `1,234`)
+    pub fn guess(s: &str) -> Option<NumberFormat> {
+        let s = s.trim();
+        let last_point = s.rfind('.');
+        let last_comma = s.rfind(',');
+        match (last_point, last_comma) {
+            // both are used, so the last one is the decimal separator
+            (Some(point), Some(comma)) if point > comma => Some(NumberFormat::DecimalPoint),
+            (Some(_), Some(_)) => Some(NumberFormat::DecimalComma),
+            // a lone separator with exactly 3 digits after it could be a thousands separator
+            (Some(point), None) if s.len() - point - 1 != 3 => Some(NumberFormat::DecimalPoint),
+            (None, Some(comma)) if s.len() - comma - 1 != 3 => Some(NumberFormat::DecimalComma),
+            _ => None,
+        }
+    }
+}
+
+/// The character between fields
+#[derive(serde::Deserialize, serde::Serialize, EnumIter, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delimiter {
+    Comma,
+    Semicolon,
+}
+
+impl std::fmt::Display for Delimiter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Delimiter::Comma => write!(f, "Comma"),
+            Delimiter::Semicolon => write!(f, "Semicolon"),
+        }
+    }
+}
+
+impl Delimiter {
+    pub fn byte(&self) -> u8 {
+        match *self {
+            Delimiter::Comma => b',',
+            Delimiter::Semicolon => b';',
+        }
+    }
+}
+
+/// Everything locale dependent about how entries are laid out in a csv file
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CsvFormat {
+    pub date: DateFormat,
+    pub number: NumberFormat,
+    pub delimiter: Delimiter,
+}
+
+impl Default for CsvFormat {
+    fn default() -> Self {
+        Self {
+            date: DateFormat::YearMonthDay,
+            number: NumberFormat::DecimalPoint,
+            delimiter: Delimiter::Comma,
+        }
+    }
+}
+
+impl std::fmt::Display for CsvFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "dates like {}, costs like {}, {} separated",
+            self.date,
+            self.number,
+            self.delimiter.to_string().to_lowercase()
+        )
+    }
+}
+
+impl CsvFormat {
+    /// Detect the format of csv `data` from its first rows. Anything that can't be decided (an empty file, or
+    /// only dates like 01/02/2024) falls back to the defaults, preferring day-first dates over month-first ones.
+    /// Costs that could be read either way follow the delimiter and dates instead.
+    /// Set `has_header` if the first row names the columns in the usual order rather than holding an entry
+    pub fn detect(data: &[u8], has_header: bool) -> CsvFormat {
+        let mut format = CsvFormat::default();
+
+        // a decimal comma forces another delimiter, so whichever splits the first line into more fields wins
+        let first_line = data.split(|&b| b == b'\n').next().unwrap_or_default();
+        let count = |byte: u8| first_line.iter().filter(|&&b| b == byte).count();
+        if count(b';') > count(b',') {
+            format.delimiter = Delimiter::Semicolon;
+        }
+
+        let mut rdr = ReaderBuilder::new()
+            .has_headers(has_header)
+            .flexible(true)
+            .delimiter(format.delimiter.byte())
+            .from_reader(data);
+
+        // columns are named in a header, otherwise they're always name, date, cost, category
+        let (date_col, cost_col) = match rdr.headers() {
+            Ok(header) if has_header => {
+                let find = |name: &str| header.iter().position(|column| column.trim() == name);
+                (find("date").unwrap_or(1), find("cost").unwrap_or(2))
+            }
+            _ => (1, 2),
+        };
+
+        let sample: Vec<csv::StringRecord> = rdr
+            .records()
+            .filter_map(|result| result.ok())
+            .filter(|record| record.len() > date_col.max(cost_col))
+            .take(SAMPLE_ROWS)
+            .collect();
+
+        let dates: Vec<&str> = sample.iter().map(|record| &record[date_col]).collect();
+        if let Some(date) = [
+            DateFormat::YearMonthDay,
+            DateFormat::DayMonthYearDot,
+            DateFormat::DayMonthYearSlash,
+            DateFormat::MonthDayYearSlash,
+        ]
+        .into_iter()
+        .find(|date| {
+            !dates.is_empty()
+                && dates
+                    .iter()
+                    .all(|s| chrono::NaiveDate::parse_from_str(s.trim(), date.pattern()).is_ok())
+        }) {
+            format.date = date;
+        }
+
+        // the first number that isn't ambiguous decides. If every one could be read either way, go with the rest of
+        // the file: semicolons and dotted dates are written where a comma is the decimal separator
+        match sample
+            .iter()
+            .find_map(|record| NumberFormat::guess(&record[cost_col]))
+        {
+            Some(number) => format.number = number,
+            None => {
+                if format.delimiter == Delimiter::Semicolon
+                    || format.date == DateFormat::DayMonthYearDot
+                {
+                    format.number = NumberFormat::DecimalComma;
+                }
+                if sample
+                    .iter()
+                    .any(|record| record[cost_col].contains(['.', ',']))
+                {
+                    warn!(
+                        "Every cost could be read either way (like 1.234), reading them like {}",
+                        format.number
+                    );
+                }
+            }
+        }
+
+        format
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(
+            CsvFormat::detect(
+                b"Bread,2024-01-02,3.5,Groceries\nRent,2024-01-03,1200.0,Rent\n",
+                false
+            ),
+            CsvFormat::default()
+        );
+        assert_eq!(
+            CsvFormat::detect(
+                b"Brot;02.01.2024;3,50;Groceries\nMiete;03.01.2024;1.200,00;Rent\n",
+                false
+            ),
+            CsvFormat {
+                date: DateFormat::DayMonthYearDot,
+                number: NumberFormat::DecimalComma,
+                delimiter: Delimiter::Semicolon,
+            }
+        );
+        // the 13th can only be a day, so the dates are month first
+        let detected = CsvFormat::detect(b"a,01/02/2024,1.5,Misc\nb,01/13/2024,2,Misc\n", false);
+        assert_eq!(detected.date, DateFormat::MonthDayYearSlash);
+
+        // 1.234 could be either, so the dotted dates decide
+        let detected = CsvFormat::detect(b"Miete;03.01.2024;1.234;Rent\n", false);
+        assert_eq!(detected.number, NumberFormat::DecimalComma);
+        let detected = CsvFormat::detect(b"Rent,2024-01-03,1.234,Rent\n", false);
+        assert_eq!(detected.number, NumberFormat::DecimalPoint);
+    }
+
+    #[test]
+    fn test_numbers() {
+        assert_eq!(NumberFormat::DecimalComma.parse("1.234,5"), Ok(1234.5));
+        assert_eq!(NumberFormat::DecimalPoint.parse("1,234.5"), Ok(1234.5));
+        assert_eq!(NumberFormat::DecimalComma.format(3.5), "3,5");
+        assert_eq!(NumberFormat::guess("1.234"), None);
+        assert_eq!(
+            NumberFormat::guess("1.234,00"),
+            Some(NumberFormat::DecimalComma)
+        );
+    }
+}
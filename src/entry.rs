@@ -1,7 +1,9 @@
 use crate::category::Category;
+use crate::csvformat::CsvFormat;
 
 use chrono::NaiveDate;
 use csv::StringRecord;
+use std::collections::BTreeMap;
 use std::error::Error;
 use std::str::FromStr;
 
@@ -34,6 +36,11 @@ pub struct Entry {
     pub cost: Cost,
     pub date: NaiveDate,
     pub category: Category,
+
+    /// Columns from the entry's csv file that PennyPilot doesn't use, by column name. They're kept so that
+    /// writing the file back doesn't lose them
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub extra: BTreeMap<String, String>,
 }
 
 impl PartialEq for Entry {
@@ -59,41 +66,39 @@ impl Default for Entry {
             cost: Cost::try_from(0.0).unwrap(),
             date: NaiveDate::from_ymd_opt(1970, 1, 1).unwrap(),
             category: Category::Misc,
+            extra: BTreeMap::new(),
         }
     }
 }
 
-impl TryFrom<StringRecord> for Entry {
-    type Error = Box<dyn Error>;
-
-    fn try_from(record: StringRecord) -> Result<Self, Self::Error> {
+impl Entry {
+    /// Parse a csv record laid out as: name, date, cost, category
+    pub fn from_record(record: &StringRecord, format: &CsvFormat) -> Result<Self, Box<dyn Error>> {
         if record.len() != 4 {
             return Err("Record must have exactly 4 fields".into());
         }
 
         let name = record[0].to_string();
-        let date = NaiveDate::parse_from_str(&record[1], "%Y-%m-%d")?;
-        let cost = record[2].parse::<f32>()?;
-        let category = Category::from_str(&record[3])?;
+        let date = NaiveDate::parse_from_str(record[1].trim(), format.date.pattern())?;
+        let cost = format.number.parse(&record[2])?;
+        let category = Category::from_str(record[3].trim())?;
 
         Ok(Entry {
             name,
             cost: Cost::try_from(cost).map_err(|_| "Invalid cost")?,
             date,
             category,
+            extra: BTreeMap::new(),
         })
     }
-}
 
-impl Entry {
-    /// Todo: convert this to produce a StringRecord and make this use the csv crate interface on the other side too?
-    pub fn to_csv_string(&self) -> String {
-        format!(
-            "{},{},{:?},{}",
-            self.name,
-            self.date,
-            Into::<f32>::into(self.cost),
-            self.category
-        )
+    /// The inverse of `from_record`. Extra columns aren't included
+    pub fn to_record(&self, format: &CsvFormat) -> StringRecord {
+        StringRecord::from(vec![
+            self.name.clone(),
+            self.date.format(format.date.pattern()).to_string(),
+            format.number.format(self.cost.into()),
+            self.category.to_string(),
+        ])
     }
 }
@@ -0,0 +1,105 @@
+use crate::category::Category;
+use chrono::NaiveDate;
+
+/// How many months before the current one a forecast is fitted to
+pub const HISTORY_MONTHS: u32 = 12;
+/// A charge is recurring if an entry with its name shows up in each of this many months before the current one
+pub const RECURRING_MONTHS: u32 = 3;
+
+/// Where spending in a category is projected to land by the end of a month. `Category::All` covers every category
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Forecast {
+    pub category: Category,
+    /// First and last day of the month
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    /// The day the forecast was made on. Everything through it has already been spent
+    pub today: NaiveDate,
+    pub spent: f32,
+    /// Recurring charges that came every month before but haven't come yet this month
+    pub recurring: f32,
+    /// The expected total by the end of the month
+    pub projected: f32,
+    /// The range the total is likely to land in
+    pub low: f32,
+    pub high: f32,
+}
+
+impl std::fmt::Display for Forecast {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "${:.2} by {} (${:.2} to ${:.2}), ${:.2} spent so far",
+            self.projected, self.end, self.low, self.high, self.spent
+        )
+    }
+}
+
+/// The total expected for the month after `history` (oldest first), along with how far off it's likely to be. A
+/// straight line is fitted through the history. With a year of it, the trend is scaled by how the month a year
+/// before compared to that year's average, so seasonal spending like the holidays is expected again
+pub fn predict(history: &[f32]) -> (f32, f32) {
+    let n = history.len() as f32;
+    if history.is_empty() {
+        return (0.0, 0.0);
+    }
+    let mean_x = (n - 1.0) / 2.0;
+    let mean_y = history.iter().sum::<f32>() / n;
+    let (covariance, variance) =
+        history
+            .iter()
+            .enumerate()
+            .fold((0.0, 0.0), |(cov, var), (x, y)| {
+                let dx = x as f32 - mean_x;
+                (cov + dx * (y - mean_y), var + dx * dx)
+            });
+    let slope = if variance > 0.0 {
+        covariance / variance
+    } else {
+        0.0
+    };
+    let fitted = |x: f32| mean_y + slope * (x - mean_x);
+
+    let spread = (history
+        .iter()
+        .enumerate()
+        .map(|(x, y)| (y - fitted(x as f32)).powi(2))
+        .sum::<f32>()
+        / n)
+        .sqrt();
+
+    let mut expected = fitted(n);
+    if let Some(year) = history.len().checked_sub(12).map(|start| &history[start..]) {
+        let average = year.iter().sum::<f32>() / 12.0;
+        if average > 0.0 {
+            expected *= year[0] / average;
+        }
+    }
+    (expected.max(0.0), spread)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_predict() {
+        assert_eq!(predict(&[]), (0.0, 0.0));
+        assert_eq!(predict(&[50.0]), (50.0, 0.0));
+        // a steady climb continues, and fits exactly
+        assert_eq!(predict(&[10.0, 20.0, 30.0]), (40.0, 0.0));
+        // a steady drop doesn't go below nothing
+        assert_eq!(predict(&[30.0, 20.0, 10.0, 0.0]).0, 0.0);
+
+        let (expected, spread) = predict(&[10.0, 30.0, 10.0, 30.0]);
+        assert!((expected - 30.0).abs() < 0.01);
+        assert!(spread > 0.0);
+
+        // the month a year before was double the others, so this one is expected to be high too
+        let mut history = vec![100.0; 12];
+        history[0] = 200.0;
+        assert!(predict(&history).0 > 150.0);
+        history.push(100.0);
+        assert!((predict(&history[1..]).0 - 100.0).abs() < 0.01);
+    }
+}
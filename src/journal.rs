@@ -0,0 +1,532 @@
+use crate::category::Category;
+use crate::csvformat::NumberFormat;
+use crate::entry::{Cost, Entry};
+
+use chrono::NaiveDate;
+use std::error::Error;
+use std::fmt::Write;
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+/// The commodity amounts are written in. PennyPilot only deals in dollars
+const CURRENCY: &str = "USD";
+
+/// Every category is posted to a sub account of this one
+const EXPENSES_ROOT: &str = "Expenses";
+
+/// Plain-text accounting formats entries can be exported to and imported from
+#[derive(serde::Deserialize, serde::Serialize, EnumIter, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JournalFormat {
+    /// ledger and hledger journals, which share a syntax for everything we use
+    Ledger,
+    Beancount,
+}
+
+impl std::fmt::Display for JournalFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            JournalFormat::Ledger => write!(f, "Ledger / hledger"),
+            JournalFormat::Beancount => write!(f, "Beancount"),
+        }
+    }
+}
+
+impl JournalFormat {
+    #[cfg(not(target_arch = "wasm32"))]
+    /// File extension used for the format
+    pub fn extension(&self) -> &'static str {
+        match *self {
+            JournalFormat::Ledger => "journal",
+            JournalFormat::Beancount => "beancount",
+        }
+    }
+}
+
+/// The account an entry's category is posted to, e.g. `Expenses:OtherFood`
+pub fn category_account(category: Category) -> String {
+    // the Debug name has no spaces, which beancount requires
+    format!("{}:{:?}", EXPENSES_ROOT, category)
+}
+
+/// Map an expense account back to a category. Sub accounts count towards their parent (`Expenses:Car:Gas` is `Car`)
+/// and anything that isn't a PennyPilot category ends up in `Misc`.
+/// Returns None for accounts that aren't expenses at all
+fn account_category(account: &str) -> Option<Category> {
+    let mut components = account.split(':');
+    if components.next()? != EXPENSES_ROOT {
+        return None;
+    }
+    let name: String = components
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect();
+
+    let category = Category::iter()
+        .filter(|&category| category != Category::All)
+        .find(|category| {
+            let display: String = category
+                .to_string()
+                .chars()
+                .filter(|c| !c.is_whitespace())
+                .collect();
+            format!("{:?}", category).eq_ignore_ascii_case(&name)
+                || display.eq_ignore_ascii_case(&name)
+        });
+    Some(category.unwrap_or_else(|| {
+        warn!(
+            "No category for account {}, using {}",
+            account,
+            Category::Misc
+        );
+        Category::Misc
+    }))
+}
+
+/// Write each entry as a balanced transaction that moves its cost from `funding_account` to the entry's
+/// category account. Transactions are written in date order regardless of how `entries` is sorted
+pub fn write_journal<'a>(
+    entries: impl IntoIterator<Item = &'a Entry>,
+    format: JournalFormat,
+    funding_account: &str,
+) -> String {
+    let mut sorted: Vec<&Entry> = entries.into_iter().collect();
+    sorted.sort_by_key(|entry| entry.date);
+
+    let mut out = String::new();
+    // writing to a String can't fail, so the results are ignored below
+    match format {
+        JournalFormat::Ledger => {
+            let _ = writeln!(out, "; exported from PennyPilot");
+            for entry in sorted {
+                let cost: f32 = entry.cost.into();
+                // a `;` would start a comment, so it's escaped along with the backslash that escapes it
+                let name = entry.name.replace('\\', "\\\\").replace(';', "\\;");
+                let _ = writeln!(out, "\n{} {}", entry.date, name);
+                let _ = writeln!(
+                    out,
+                    "    {}  ${:.2}",
+                    category_account(entry.category),
+                    cost
+                );
+                let _ = writeln!(out, "    {}  $-{:.2}", funding_account, cost);
+            }
+        }
+        JournalFormat::Beancount => {
+            let _ = writeln!(out, "; exported from PennyPilot");
+            let _ = writeln!(out, "option \"operating_currency\" \"{}\"", CURRENCY);
+
+            // beancount wants every account opened before it's used
+            if let Some(first) = sorted.first() {
+                let mut accounts: Vec<String> = sorted
+                    .iter()
+                    .map(|entry| category_account(entry.category))
+                    .collect();
+                accounts.sort();
+                accounts.dedup();
+                accounts.push(funding_account.to_string());
+
+                let _ = writeln!(out);
+                for account in accounts {
+                    let _ = writeln!(out, "{} open {}", first.date, account);
+                }
+            }
+
+            for entry in sorted {
+                let cost: f32 = entry.cost.into();
+                let name = entry.name.replace('\\', "\\\\").replace('"', "\\\"");
+                let _ = writeln!(out, "\n{} * \"{}\"", entry.date, name);
+                let _ = writeln!(
+                    out,
+                    "  {}  {:.2} {}",
+                    category_account(entry.category),
+                    cost,
+                    CURRENCY
+                );
+                let _ = writeln!(out, "  {}  -{:.2} {}", funding_account, cost, CURRENCY);
+            }
+        }
+    }
+    out
+}
+
+/// A posting's account and amount. The amount is None when it's left for the journal to balance
+type Posting = (String, Option<f32>);
+
+/// A transaction that's been parsed but not yet turned into entries
+struct Transaction {
+    line: usize,
+    date: NaiveDate,
+    name: String,
+    postings: Vec<Posting>,
+}
+
+impl Transaction {
+    /// Make sure the postings sum to zero, then build an entry for every expense posting
+    fn into_entries(self) -> Result<Vec<Entry>, Box<dyn Error>> {
+        let total: f32 = self.postings.iter().filter_map(|(_, amount)| *amount).sum();
+        let elided = self
+            .postings
+            .iter()
+            .filter(|(_, amount)| amount.is_none())
+            .count();
+        match elided {
+            0 if total.abs() >= 0.005 => {
+                return Err(format!(
+                    "Transaction on line {} doesn't balance (off by {:.2})",
+                    self.line, total
+                )
+                .into())
+            }
+            0 | 1 => {}
+            _ => {
+                return Err(format!(
+                    "Transaction on line {} has more than one posting without an amount",
+                    self.line
+                )
+                .into())
+            }
+        }
+
+        let mut entries = vec![];
+        for (account, amount) in &self.postings {
+            let Some(category) = account_category(account) else {
+                continue;
+            };
+            let amount = amount.unwrap_or(-total);
+            match Cost::try_from(amount) {
+                Ok(cost) => entries.push(Entry {
+                    name: self.name.clone(),
+                    cost,
+                    date: self.date,
+                    category,
+                    ..Default::default()
+                }),
+                // refunds and the like can't be represented as an entry
+                Err(_) => warn!(
+                    "Skipping negative expense posting on line {} ({} {})",
+                    self.line, account, amount
+                ),
+            }
+        }
+        Ok(entries)
+    }
+}
+
+/// Read the entries back out of a journal. Every posting to an `Expenses:` account becomes an entry named after
+/// the transaction's payee (or narration in beancount when there's no payee). Other directives are ignored
+pub fn read_journal(text: &str, format: JournalFormat) -> Result<Vec<Entry>, Box<dyn Error>> {
+    let mut entries = vec![];
+    let mut current: Option<Transaction> = None;
+
+    for (idx, raw_line) in text.lines().enumerate() {
+        let line_num = idx + 1;
+        let line = strip_comment(raw_line);
+
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let indented = line.starts_with(|c: char| c.is_whitespace());
+        if indented {
+            // postings only mean something inside a transaction
+            if let Some(transaction) = current.as_mut() {
+                if let Some(posting) = parse_posting(line.trim(), format, line_num)? {
+                    transaction.postings.push(posting);
+                }
+            }
+            continue;
+        }
+
+        // anything that isn't indented ends the current transaction
+        if let Some(transaction) = current.take() {
+            entries.extend(transaction.into_entries()?);
+        }
+        if line.starts_with(|c: char| c.is_ascii_digit()) {
+            current = parse_header(line, format, line_num)?;
+        }
+    }
+    if let Some(transaction) = current.take() {
+        entries.extend(transaction.into_entries()?);
+    }
+
+    Ok(entries)
+}
+
+/// Drop a trailing `;` comment. Full line comments in either format end up empty.
+/// Semicolons inside beancount strings, and ones escaped with a backslash, are left alone
+fn strip_comment(line: &str) -> &str {
+    let trimmed = line.trim_start();
+    if trimmed.starts_with(['#', '*', '%', '|']) && !line.starts_with(char::is_whitespace) {
+        return "";
+    }
+
+    let mut in_string = false;
+    let mut chars = line.char_indices();
+    while let Some((idx, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '"' => in_string = !in_string,
+            ';' if !in_string => return &line[..idx],
+            _ => {}
+        }
+    }
+    line
+}
+
+/// Undo the escaping `write_journal` does to ledger payees. Other backslashes are kept as they are
+fn unescape_payee(payee: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = payee.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('\\', Some(&next)) if next == '\\' || next == ';' => {
+                unescaped.push(next);
+                chars.next();
+            }
+            _ => unescaped.push(c),
+        }
+    }
+    unescaped
+}
+
+/// Parse a dated line. Returns None for directives that aren't transactions (beancount `open`, `price`, ...)
+fn parse_header(
+    line: &str,
+    format: JournalFormat,
+    line_num: usize,
+) -> Result<Option<Transaction>, Box<dyn Error>> {
+    let (date_str, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    // ledger allows a secondary date after '='
+    let date_str = date_str.split('=').next().unwrap_or_default();
+    let date = ["%Y-%m-%d", "%Y/%m/%d", "%Y.%m.%d"]
+        .iter()
+        .find_map(|fmt| NaiveDate::parse_from_str(date_str, fmt).ok())
+        .ok_or_else(|| format!("Invalid date \"{}\" on line {}", date_str, line_num))?;
+    let rest = rest.trim();
+
+    let name = match format {
+        JournalFormat::Ledger => {
+            // optional status flag and (code) before the description
+            let rest = rest.trim_start_matches(['*', '!']).trim_start();
+            let rest = match rest.strip_prefix('(') {
+                Some(code) => code.split_once(')').map_or("", |(_, desc)| desc),
+                None => rest,
+            };
+            unescape_payee(rest.trim())
+        }
+        JournalFormat::Beancount => {
+            let Some(rest) = ["*", "!", "txn"]
+                .iter()
+                .find_map(|flag| rest.strip_prefix(flag))
+            else {
+                return Ok(None);
+            };
+            let strings = quoted_strings(rest);
+            match strings.as_slice() {
+                [narration] => narration.clone(),
+                [payee, narration, ..] if payee.is_empty() => narration.clone(),
+                [payee, ..] => payee.clone(),
+                [] => String::new(),
+            }
+        }
+    };
+
+    Ok(Some(Transaction {
+        line: line_num,
+        date,
+        name,
+        postings: vec![],
+    }))
+}
+
+/// Every double quoted string in `s`, unescaped
+fn quoted_strings(s: &str) -> Vec<String> {
+    let mut strings = vec![];
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '"' {
+            continue;
+        }
+        let mut string = String::new();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => string.extend(chars.next()),
+                '"' => break,
+                _ => string.push(c),
+            }
+        }
+        strings.push(string);
+    }
+    strings
+}
+
+/// Parse a posting into its account and amount. Returns None for beancount metadata lines
+fn parse_posting(
+    line: &str,
+    format: JournalFormat,
+    line_num: usize,
+) -> Result<Option<Posting>, Box<dyn Error>> {
+    let line = line.trim_start_matches(['*', '!']).trim_start();
+    let (account, amount) = match format {
+        // ledger accounts can contain single spaces, so the amount is separated by two spaces or a tab
+        JournalFormat::Ledger => {
+            let split = [line.find("  "), line.find('\t')]
+                .into_iter()
+                .flatten()
+                .min();
+            match split {
+                Some(idx) => (&line[..idx], line[idx..].trim()),
+                None => (line, ""),
+            }
+        }
+        JournalFormat::Beancount => {
+            // metadata (`key: value`) starts with a lower case letter, accounts never do
+            if line.starts_with(|c: char| c.is_lowercase()) {
+                return Ok(None);
+            }
+            line.split_once(char::is_whitespace)
+                .map_or((line, ""), |(account, amount)| (account, amount.trim()))
+        }
+    };
+
+    if amount.is_empty() {
+        return Ok(Some((account.to_string(), None)));
+    }
+
+    // only the posted amount matters - drop balance assertions, prices and lot costs
+    let amount = amount.split(['=', '@', '{']).next().unwrap_or_default();
+    let number: String = amount
+        .chars()
+        .filter(|c| c.is_ascii_digit() || matches!(c, '.' | ',' | '-'))
+        .collect();
+    // read the separators like a csv's costs are. If it could go either way (`1,234`), the comma groups thousands
+    let value = NumberFormat::guess(&number)
+        .unwrap_or(NumberFormat::DecimalPoint)
+        .parse(&number)
+        .map_err(|_| format!("Invalid amount \"{}\" on line {}", amount.trim(), line_num))?;
+
+    Ok(Some((account.to_string(), Some(value))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries() -> Vec<Entry> {
+        vec![
+            Entry {
+                name: "Farmers \"market\"".to_string(),
+                cost: Cost::try_from(12.5).unwrap(),
+                date: NaiveDate::from_ymd_opt(2023, 6, 3).unwrap(),
+                category: Category::Groceries,
+                ..Default::default()
+            },
+            Entry {
+                name: "Pizza".to_string(),
+                cost: Cost::try_from(21.0).unwrap(),
+                date: NaiveDate::from_ymd_opt(2023, 6, 1).unwrap(),
+                category: Category::OtherFood,
+                ..Default::default()
+            },
+        ]
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for format in JournalFormat::iter() {
+            let journal = write_journal(&entries(), format, "Assets:Checking");
+            let mut read = read_journal(&journal, format).unwrap();
+            read.reverse(); // journals are written in date order
+            assert_eq!(read, entries(), "{} round trip failed", format);
+            for (read, expected) in read.iter().zip(entries()) {
+                assert_eq!(read.cost, expected.cost);
+            }
+        }
+    }
+
+    #[test]
+    fn test_payee_round_trip() {
+        let mut entries = entries();
+        entries[0].name = "Bäckerei; Markt \\ Café".to_string();
+        for format in JournalFormat::iter() {
+            let journal = write_journal(&entries, format, "Assets:Checking");
+            let read = read_journal(&journal, format).unwrap();
+            assert_eq!(
+                read[1].name, entries[0].name,
+                "{} round trip failed",
+                format
+            );
+        }
+        // a backslash that doesn't escape anything is part of the name
+        let journal = "2023-01-01 AC\\DC ; tickets\n    Expenses:Misc  $50\n    Assets:Cash\n";
+        let read = read_journal(journal, JournalFormat::Ledger).unwrap();
+        assert_eq!(read[0].name, "AC\\DC");
+    }
+
+    #[test]
+    fn test_read_ledger() {
+        let journal = "\
+; a comment
+2023/01/05 * (1042) Hardware Store  ; trip to town
+    Expenses:Car:Parts     $1,200.00
+    Expenses:Boat           $30.00
+    Liabilities:Visa
+
+2023-01-06 Paycheck
+    Assets:Checking  $-500
+    Income:Salary    $500
+";
+        let entries = read_journal(journal, JournalFormat::Ledger).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].name, "Hardware Store");
+        assert_eq!(entries[0].category, Category::Car);
+        assert_eq!(entries[0].cost, Cost::try_from(1200.0).unwrap());
+        // unknown expense accounts go to Misc
+        assert_eq!(entries[1].category, Category::Misc);
+    }
+
+    #[test]
+    fn test_read_beancount() {
+        let journal = "\
+2023-01-01 open Expenses:Groceries
+2023-02-01 * \"Grocer\" \"Weekly shop\" #food
+  receipt: \"scan.pdf\"
+  Expenses:Groceries  40.00 USD
+  Assets:Cash
+";
+        let entries = read_journal(journal, JournalFormat::Beancount).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].name, "Grocer");
+        assert_eq!(entries[0].cost, Cost::try_from(40.0).unwrap());
+    }
+
+    #[test]
+    fn test_decimal_comma() {
+        let journal = "\
+2023-03-01 Bakery
+    Expenses:Groceries  EUR 1.234,56
+    Assets:Checking
+
+2023-03-02 Cafe
+    Expenses:Groceries  12,50
+    Assets:Checking
+";
+        let entries = read_journal(journal, JournalFormat::Ledger).unwrap();
+        assert_eq!(entries[0].cost, Cost::try_from(1234.56).unwrap());
+        assert_eq!(entries[1].cost, Cost::try_from(12.5).unwrap());
+    }
+
+    #[test]
+    fn test_unbalanced() {
+        let journal = "\
+2023-01-01 Oops
+    Expenses:Rent  $100
+    Assets:Checking  $-90
+";
+        assert!(read_journal(journal, JournalFormat::Ledger).is_err());
+    }
+}
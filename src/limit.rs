@@ -0,0 +1,287 @@
+use crate::payperiod::PaySchedule;
+use chrono::{Datelike, Duration, NaiveDate};
+use strum_macros::EnumIter;
+
+/// The span of time a spending limit applies to. Every period except `Custom` repeats
+#[derive(serde::Deserialize, serde::Serialize, EnumIter, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitPeriod {
+    Weekly,
+    Monthly,
+    Quarterly,
+    Yearly,
+    PayPeriod,
+    Custom,
+}
+
+impl std::fmt::Display for LimitPeriod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            LimitPeriod::Weekly => write!(f, "Weekly"),
+            LimitPeriod::Monthly => write!(f, "Monthly"),
+            LimitPeriod::Quarterly => write!(f, "Quarterly"),
+            LimitPeriod::Yearly => write!(f, "Yearly"),
+            LimitPeriod::PayPeriod => write!(f, "Per Pay Period"),
+            LimitPeriod::Custom => write!(f, "Custom"),
+        }
+    }
+}
+
+/// A spending limit for a category, or for all of them together with `Category::All`
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq)]
+#[serde(from = "StoredLimit")]
+pub struct Limit {
+    pub amount: f32,
+    pub period: LimitPeriod,
+    /// The first and last days of a `Custom` period. The other periods ignore them
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+}
+
+/// Limits used to be a bare monthly amount. Accept those as well so older settings still load
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum StoredLimit {
+    Amount(f32),
+    Limit {
+        amount: f32,
+        period: LimitPeriod,
+        start: NaiveDate,
+        end: NaiveDate,
+    },
+}
+
+impl From<StoredLimit> for Limit {
+    fn from(stored: StoredLimit) -> Self {
+        match stored {
+            StoredLimit::Amount(amount) => Limit::monthly(amount),
+            StoredLimit::Limit {
+                amount,
+                period,
+                start,
+                end,
+            } => Limit {
+                amount,
+                period,
+                start,
+                end,
+            },
+        }
+    }
+}
+
+impl Limit {
+    pub fn monthly(amount: f32) -> Self {
+        let today = chrono::Local::now().date_naive();
+        Limit {
+            amount,
+            period: LimitPeriod::Monthly,
+            start: today,
+            end: today,
+        }
+    }
+
+    /// The first and last day of the period that contains `date`, with pay periods following `pay_schedule`. None
+    /// if the period is custom, or a custom pay period, and doesn't contain it
+    pub fn range_containing(
+        &self,
+        date: NaiveDate,
+        pay_schedule: &PaySchedule,
+    ) -> Option<(NaiveDate, NaiveDate)> {
+        let month_start = |year: i32, month: u32| NaiveDate::from_ymd_opt(year, month, 1).unwrap();
+        // the day before the first day of the month `months` after the given one
+        let months_end = |year: i32, month: u32, months: u32| {
+            let next = month - 1 + months;
+            month_start(year + (next / 12) as i32, next % 12 + 1) - Duration::days(1)
+        };
+
+        match self.period {
+            LimitPeriod::Weekly => {
+                let start = date - Duration::days(date.weekday().num_days_from_monday() as i64);
+                Some((start, start + Duration::days(6)))
+            }
+            LimitPeriod::Monthly => Some((
+                month_start(date.year(), date.month()),
+                months_end(date.year(), date.month(), 1),
+            )),
+            LimitPeriod::Quarterly => {
+                let first_month = (date.month() - 1) / 3 * 3 + 1;
+                Some((
+                    month_start(date.year(), first_month),
+                    months_end(date.year(), first_month, 3),
+                ))
+            }
+            LimitPeriod::Yearly => {
+                Some((month_start(date.year(), 1), months_end(date.year(), 1, 12)))
+            }
+            LimitPeriod::PayPeriod => pay_schedule.range_containing(date),
+            LimitPeriod::Custom => {
+                (self.start <= date && date <= self.end).then_some((self.start, self.end))
+            }
+        }
+    }
+
+    /// The share of the limit that applies from `start` to `end`, inclusive. Each day gets an equal part of the
+    /// period it's in, so a monthly limit over a whole year is twelve times the limit and a weekly one over a
+    /// single day is a seventh of it. Adds up one period at a time rather than one day at a time
+    pub fn prorated(&self, start: NaiveDate, end: NaiveDate, pay_schedule: &PaySchedule) -> f32 {
+        // only the days some period covers count
+        let (start, end) = match (self.period, pay_schedule) {
+            (LimitPeriod::Custom, _) => (start.max(self.start), end.min(self.end)),
+            (LimitPeriod::PayPeriod, PaySchedule::Custom(paydays)) => match paydays.covered() {
+                Some((first, last)) => (start.max(first), end.min(last)),
+                None => return 0.0,
+            },
+            _ => (start, end),
+        };
+
+        let mut total = 0.0;
+        let mut day = start;
+        while day <= end {
+            let Some((period_start, period_end)) = self.range_containing(day, pay_schedule) else {
+                break;
+            };
+            let days = (period_end.min(end) - day).num_days() + 1;
+            let period_days = (period_end - period_start).num_days() + 1;
+            total += self.amount * days as f32 / period_days as f32;
+            day = period_end + Duration::days(1);
+        }
+        total
+    }
+}
+
+/// How much of a limit has been spent during one of its periods
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LimitStatus {
+    /// First and last day of the period, inclusive
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    pub spent: f32,
+    pub amount: f32,
+}
+
+impl std::fmt::Display for LimitStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "${:.2} of ${:.2} from {} to {}",
+            self.spent, self.amount, self.start, self.end
+        )
+    }
+}
+
+impl LimitStatus {
+    /// Negative once the limit is exceeded
+    pub fn remaining(&self) -> f32 {
+        self.amount - self.spent
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn test_range_containing() {
+        let schedule = PaySchedule::default();
+        let mut limit = Limit::monthly(100.0);
+        let day = date(2023, 12, 13); // a wednesday
+        assert_eq!(
+            limit.range_containing(day, &schedule),
+            Some((date(2023, 12, 1), date(2023, 12, 31)))
+        );
+
+        limit.period = LimitPeriod::Weekly;
+        assert_eq!(
+            limit.range_containing(day, &schedule),
+            Some((date(2023, 12, 11), date(2023, 12, 17)))
+        );
+
+        limit.period = LimitPeriod::Quarterly;
+        assert_eq!(
+            limit.range_containing(day, &schedule),
+            Some((date(2023, 10, 1), date(2023, 12, 31)))
+        );
+        assert_eq!(
+            limit.range_containing(date(2024, 2, 29), &schedule),
+            Some((date(2024, 1, 1), date(2024, 3, 31)))
+        );
+
+        limit.period = LimitPeriod::Yearly;
+        assert_eq!(
+            limit.range_containing(day, &schedule),
+            Some((date(2023, 1, 1), date(2023, 12, 31)))
+        );
+
+        limit.period = LimitPeriod::PayPeriod;
+        let schedule = PaySchedule::Biweekly {
+            anchor: date(2023, 12, 1),
+        };
+        assert_eq!(
+            limit.range_containing(day, &schedule),
+            Some((date(2023, 12, 1), date(2023, 12, 14)))
+        );
+
+        limit.period = LimitPeriod::Custom;
+        limit.start = date(2023, 12, 1);
+        limit.end = date(2023, 12, 10);
+        assert_eq!(limit.range_containing(day, &schedule), None);
+        assert_eq!(
+            limit.range_containing(date(2023, 12, 10), &schedule),
+            Some((date(2023, 12, 1), date(2023, 12, 10)))
+        );
+    }
+
+    #[test]
+    fn test_prorated() {
+        let schedule = PaySchedule::default();
+        let mut limit = Limit::monthly(310.0);
+        let close = |a: f32, b: f32| (a - b).abs() < 0.01;
+        assert!(close(
+            limit.prorated(date(2023, 1, 1), date(2023, 1, 31), &schedule),
+            310.0
+        ));
+        assert!(close(
+            limit.prorated(date(2023, 1, 1), date(2023, 12, 31), &schedule),
+            3720.0
+        ));
+        assert!(close(
+            limit.prorated(date(2023, 1, 5), date(2023, 1, 5), &schedule),
+            10.0
+        ));
+
+        limit.period = LimitPeriod::Custom;
+        limit.start = date(2023, 1, 1);
+        limit.end = date(2023, 1, 10);
+        // only the days in the custom range count
+        assert!(close(
+            limit.prorated(date(2023, 1, 1), date(2023, 1, 31), &schedule),
+            310.0
+        ));
+
+        // and only the days between the first and last custom paydays
+        limit.period = LimitPeriod::PayPeriod;
+        let schedule = PaySchedule::Custom(vec![date(2023, 1, 10), date(2023, 1, 20)].into());
+        assert!(close(
+            limit.prorated(date(2023, 1, 1), date(2023, 1, 14), &schedule),
+            155.0
+        ));
+        assert!(close(
+            limit.prorated(date(2022, 1, 1), date(2024, 1, 1), &schedule),
+            310.0
+        ));
+    }
+
+    #[test]
+    fn test_old_limits() {
+        let limit: Limit = serde_json::from_str("250.0").unwrap();
+        assert_eq!(limit.amount, 250.0);
+        assert_eq!(limit.period, LimitPeriod::Monthly);
+
+        let json = serde_json::to_string(&limit).unwrap();
+        assert_eq!(serde_json::from_str::<Limit>(&json).unwrap(), limit);
+    }
+}
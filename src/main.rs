@@ -1,12 +1,24 @@
 #![warn(clippy::all, rust_2018_idioms)]
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")] // hide console window on Windows in release
 
+mod anomaly;
 mod backend;
+mod budget;
+mod cache;
 mod category;
 mod colors;
 mod csvadapter;
+mod csvformat;
 mod entry;
+mod forecast;
+mod journal;
+mod limit;
 mod organize;
+mod payperiod;
+mod query;
+mod stats;
+mod store;
+mod subscription;
 
 mod app;
 
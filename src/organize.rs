@@ -1,18 +1,248 @@
+use crate::payperiod::PaySchedule;
+use chrono::{Datelike, Duration, Months, NaiveDate, Weekday};
+use std::borrow::Cow;
 use strum_macros::EnumIter;
 
-#[derive(serde::Deserialize, serde::Serialize, EnumIter, Clone, Copy, PartialEq, Eq)]
-pub enum GroupBy {
+/// How dates are put into groups, e.g. for the bars of the graph
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum GroupBy<'a> {
     Day,
+    /// Weeks that start on the given day
+    Week(Weekday),
     Month,
+    Quarter,
     Year,
+    /// Years that start on the first of the given month (1 through 12)
+    FiscalYear(u32),
+    /// The periods between paydays. Days a custom schedule doesn't cover are grouped on their own. The schedule is
+    /// borrowed from the app's; only caches remembering what they were built for own one
+    PayPeriod(Cow<'a, PaySchedule>),
 }
 
-impl std::fmt::Display for GroupBy {
+impl std::fmt::Display for GroupBy<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.grouping())
+    }
+}
+
+/// A grouping as it's picked and saved, without the pay schedule pay periods follow
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Grouping {
+    Day,
+    Week(Weekday),
+    Month,
+    Quarter,
+    Year,
+    FiscalYear(u32),
+    PayPeriod,
+}
+
+impl std::fmt::Display for Grouping {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Grouping::Day => write!(f, "Day"),
+            Grouping::Week(_) => write!(f, "Week"),
+            Grouping::Month => write!(f, "Month"),
+            Grouping::Quarter => write!(f, "Quarter"),
+            Grouping::Year => write!(f, "Year"),
+            Grouping::FiscalYear(_) => write!(f, "Fiscal Year"),
+            Grouping::PayPeriod => write!(f, "Pay Period"),
+        }
+    }
+}
+
+impl Grouping {
+    /// Every grouping, with weeks starting on `week_start` and fiscal years in `fiscal_start`
+    pub fn all(week_start: Weekday, fiscal_start: u32) -> [Grouping; 7] {
+        [
+            Grouping::Day,
+            Grouping::Week(week_start),
+            Grouping::Month,
+            Grouping::Quarter,
+            Grouping::Year,
+            Grouping::FiscalYear(fiscal_start),
+            Grouping::PayPeriod,
+        ]
+    }
+
+    /// Group dates this way, with pay periods following `pay_schedule`
+    pub fn group_by(self, pay_schedule: &PaySchedule) -> GroupBy<'_> {
+        match self {
+            Grouping::Day => GroupBy::Day,
+            Grouping::Week(week_start) => GroupBy::Week(week_start),
+            Grouping::Month => GroupBy::Month,
+            Grouping::Quarter => GroupBy::Quarter,
+            Grouping::Year => GroupBy::Year,
+            Grouping::FiscalYear(start_month) => GroupBy::FiscalYear(start_month),
+            Grouping::PayPeriod => GroupBy::PayPeriod(Cow::Borrowed(pay_schedule)),
+        }
+    }
+}
+
+impl GroupBy<'_> {
+    /// The grouping without its pay schedule
+    pub fn grouping(&self) -> Grouping {
+        match self {
+            GroupBy::Day => Grouping::Day,
+            GroupBy::Week(week_start) => Grouping::Week(*week_start),
+            GroupBy::Month => Grouping::Month,
+            GroupBy::Quarter => Grouping::Quarter,
+            GroupBy::Year => Grouping::Year,
+            GroupBy::FiscalYear(start_month) => Grouping::FiscalYear(*start_month),
+            GroupBy::PayPeriod(_) => Grouping::PayPeriod,
+        }
+    }
+
+    /// The same grouping with its own copy of the pay schedule, for remembering what something was grouped by
+    pub fn into_owned(self) -> GroupBy<'static> {
+        match self {
+            GroupBy::Day => GroupBy::Day,
+            GroupBy::Week(week_start) => GroupBy::Week(week_start),
+            GroupBy::Month => GroupBy::Month,
+            GroupBy::Quarter => GroupBy::Quarter,
+            GroupBy::Year => GroupBy::Year,
+            GroupBy::FiscalYear(start_month) => GroupBy::FiscalYear(start_month),
+            GroupBy::PayPeriod(schedule) => GroupBy::PayPeriod(Cow::Owned(schedule.into_owned())),
+        }
+    }
+
+    /// The first day of the group `date` falls in
+    pub fn start_of(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            GroupBy::Day => date,
+            GroupBy::Week(week_start) => {
+                let days_in = (date.weekday().num_days_from_monday() + 7
+                    - week_start.num_days_from_monday())
+                    % 7;
+                date - Duration::days(days_in as i64)
+            }
+            GroupBy::Month => NaiveDate::from_ymd_opt(date.year(), date.month(), 1).unwrap(),
+            GroupBy::Quarter => {
+                let month = (date.month() - 1) / 3 * 3 + 1;
+                NaiveDate::from_ymd_opt(date.year(), month, 1).unwrap()
+            }
+            GroupBy::Year => NaiveDate::from_ymd_opt(date.year(), 1, 1).unwrap(),
+            GroupBy::FiscalYear(start_month) => {
+                let year = if date.month() >= *start_month {
+                    date.year()
+                } else {
+                    date.year() - 1
+                };
+                NaiveDate::from_ymd_opt(year, *start_month, 1).unwrap()
+            }
+            GroupBy::PayPeriod(schedule) => schedule
+                .range_containing(date)
+                .map_or(date, |(start, _)| start),
+        }
+    }
+
+    /// The first and last day of the group that starts on `start`
+    pub fn range(&self, start: NaiveDate) -> (NaiveDate, NaiveDate) {
+        let end = match self {
+            GroupBy::Day => start,
+            GroupBy::Week(_) => start + Duration::days(6),
+            GroupBy::Month => start + Months::new(1) - Duration::days(1),
+            GroupBy::Quarter => start + Months::new(3) - Duration::days(1),
+            GroupBy::Year | GroupBy::FiscalYear(_) => start + Months::new(12) - Duration::days(1),
+            GroupBy::PayPeriod(schedule) => schedule
+                .range_containing(start)
+                .map_or(start, |(_, end)| end),
+        };
+        (start, end)
+    }
+
+    /// The start of every group from the one `first` falls in through the one `last` does
+    pub fn starts(&self, first: NaiveDate, last: NaiveDate) -> Vec<NaiveDate> {
+        let mut starts = vec![];
+        let mut start = self.start_of(first);
+        while start <= last {
+            starts.push(start);
+            start = self.range(start).1 + Duration::days(1);
+        }
+        starts
+    }
+
+    /// A short name for the group that starts on `start`, e.g. "2024-01-31", "Jan 2024" or "2024". Fiscal years
+    /// are named after the year they end in
+    pub fn label(&self, start: NaiveDate) -> String {
+        match self {
+            GroupBy::Day => start.format("%Y-%m-%d").to_string(),
+            GroupBy::Week(_) => start.format("Week of %Y-%m-%d").to_string(),
+            GroupBy::Month => start.format("%b %Y").to_string(),
+            GroupBy::Quarter => format!("Q{} {}", (start.month() - 1) / 3 + 1, start.year()),
+            GroupBy::Year => start.format("%Y").to_string(),
+            GroupBy::FiscalYear(_) => format!("FY{}", self.range(start).1.year()),
+            GroupBy::PayPeriod(_) => start.format("Paid %Y-%m-%d").to_string(),
+        }
+    }
+}
+
+/// What the graph draws
+#[derive(serde::Deserialize, serde::Serialize, EnumIter, PartialEq, Eq, Copy, Clone)]
+pub enum ChartMode {
+    /// Spending per period, stacked by category
+    Bars,
+    /// Cumulative spending through the month, next to previous months
+    Pace,
+    /// Spending per day, as a calendar heatmap
+    Calendar,
+    /// Spending per month, with two or more years side by side
+    Comparison,
+}
+
+impl std::fmt::Display for ChartMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            ChartMode::Bars => write!(f, "Bars"),
+            ChartMode::Pace => write!(f, "Monthly Pace"),
+            ChartMode::Calendar => write!(f, "Calendar"),
+            ChartMode::Comparison => write!(f, "Year over Year"),
+        }
+    }
+}
+
+/// Which dates the graph shows
+#[derive(serde::Deserialize, serde::Serialize, EnumIter, PartialEq, Eq, Copy, Clone)]
+pub enum DateRange {
+    ThisMonth,
+    LastThreeMonths,
+    YearToDate,
+    LastYear,
+    All,
+    Custom,
+}
+
+impl std::fmt::Display for DateRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            DateRange::ThisMonth => write!(f, "This Month"),
+            DateRange::LastThreeMonths => write!(f, "Last 3 Months"),
+            DateRange::YearToDate => write!(f, "Year to Date"),
+            DateRange::LastYear => write!(f, "Last Year"),
+            DateRange::All => write!(f, "All"),
+            DateRange::Custom => write!(f, "Custom"),
+        }
+    }
+}
+
+impl DateRange {
+    /// The first and last day of the range as of `today`. `custom` is used for `Custom`, and `All` has no bounds
+    pub fn bounds(
+        &self,
+        today: NaiveDate,
+        custom: (NaiveDate, NaiveDate),
+    ) -> Option<(NaiveDate, NaiveDate)> {
+        let month_start = GroupBy::Month.start_of(today);
+        let year_start = GroupBy::Year.start_of(today);
         match *self {
-            GroupBy::Day => write!(f, "Day"),
-            GroupBy::Month => write!(f, "Month"),
-            GroupBy::Year => write!(f, "Year"),
+            DateRange::ThisMonth => Some((month_start, today)),
+            DateRange::LastThreeMonths => Some((month_start - Months::new(2), today)),
+            DateRange::YearToDate => Some((year_start, today)),
+            DateRange::LastYear => {
+                Some((year_start - Months::new(12), year_start - Duration::days(1)))
+            }
+            DateRange::All => None,
+            DateRange::Custom => Some(custom),
         }
     }
 }
@@ -46,3 +276,44 @@ impl std::fmt::Display for SortOrder {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_groupings() {
+        let date = |year, month, day| NaiveDate::from_ymd_opt(year, month, day).unwrap();
+
+        // 2024-01-03 is a Wednesday
+        let week = GroupBy::Week(Weekday::Sun);
+        assert_eq!(week.start_of(date(2024, 1, 3)), date(2023, 12, 31));
+        assert_eq!(week.range(date(2023, 12, 31)).1, date(2024, 1, 6));
+        assert_eq!(
+            GroupBy::Week(Weekday::Wed).start_of(date(2024, 1, 3)),
+            date(2024, 1, 3)
+        );
+
+        assert_eq!(
+            GroupBy::Quarter.start_of(date(2024, 6, 30)),
+            date(2024, 4, 1)
+        );
+        assert_eq!(
+            GroupBy::Quarter.range(date(2024, 4, 1)).1,
+            date(2024, 6, 30)
+        );
+        assert_eq!(GroupBy::Quarter.label(date(2024, 4, 1)), "Q2 2024");
+
+        let fiscal = GroupBy::FiscalYear(7);
+        assert_eq!(fiscal.start_of(date(2024, 3, 1)), date(2023, 7, 1));
+        assert_eq!(fiscal.start_of(date(2024, 7, 1)), date(2024, 7, 1));
+        assert_eq!(fiscal.range(date(2023, 7, 1)).1, date(2024, 6, 30));
+        assert_eq!(fiscal.label(date(2023, 7, 1)), "FY2024");
+        assert_eq!(GroupBy::FiscalYear(1).label(date(2024, 1, 1)), "FY2024");
+
+        assert_eq!(
+            GroupBy::Quarter.starts(date(2023, 11, 15), date(2024, 4, 1)),
+            [date(2023, 10, 1), date(2024, 1, 1), date(2024, 4, 1)]
+        );
+    }
+}
@@ -0,0 +1,163 @@
+use chrono::{Datelike, Duration, Months, NaiveDate};
+
+/// When paychecks arrive. A pay period runs from one payday to the day before the next
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Default, PartialEq, Eq)]
+pub enum PaySchedule {
+    /// Every other week, on the same weekday as `anchor`
+    Biweekly { anchor: NaiveDate },
+    /// On the 1st and the 15th of every month
+    #[default]
+    SemiMonthly,
+    /// On each of these days. The last one only ends the period before it, since the next payday isn't known
+    Custom(Paydays),
+}
+
+/// The days of a custom pay schedule, always sorted and without repeats so the period containing a day can be
+/// found with a binary search. Written out as a plain list
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(from = "Vec<NaiveDate>", into = "Vec<NaiveDate>")]
+pub struct Paydays(Vec<NaiveDate>);
+
+impl From<Vec<NaiveDate>> for Paydays {
+    fn from(mut days: Vec<NaiveDate>) -> Self {
+        days.sort_unstable();
+        days.dedup();
+        Paydays(days)
+    }
+}
+
+impl From<Paydays> for Vec<NaiveDate> {
+    fn from(paydays: Paydays) -> Self {
+        paydays.0
+    }
+}
+
+impl Paydays {
+    /// Add `day` where it belongs. Does nothing if it's already a payday
+    pub fn insert(&mut self, day: NaiveDate) {
+        if let Err(index) = self.0.binary_search(&day) {
+            self.0.insert(index, day);
+        }
+    }
+
+    /// Remove the `index`th payday, counting from the earliest
+    pub fn remove(&mut self, index: usize) {
+        self.0.remove(index);
+    }
+
+    pub fn contains(&self, day: &NaiveDate) -> bool {
+        self.0.binary_search(day).is_ok()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The first and last day the periods between these paydays cover. None without at least two of them
+    pub fn covered(&self) -> Option<(NaiveDate, NaiveDate)> {
+        match self.0.as_slice() {
+            [first, .., last] => Some((*first, *last - Duration::days(1))),
+            _ => None,
+        }
+    }
+
+    /// Every payday, earliest first
+    pub fn iter(&self) -> impl Iterator<Item = &NaiveDate> {
+        self.0.iter()
+    }
+}
+
+impl std::fmt::Display for PaySchedule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            PaySchedule::Biweekly { .. } => write!(f, "Biweekly"),
+            PaySchedule::SemiMonthly => write!(f, "Semi-Monthly"),
+            PaySchedule::Custom(_) => write!(f, "Custom"),
+        }
+    }
+}
+
+impl PaySchedule {
+    /// The first and last day of the pay period that contains `date`. None if a custom schedule doesn't cover it
+    pub fn range_containing(&self, date: NaiveDate) -> Option<(NaiveDate, NaiveDate)> {
+        match self {
+            PaySchedule::Biweekly { anchor } => {
+                let periods = (date - *anchor).num_days().div_euclid(14);
+                let start = *anchor + Duration::days(periods * 14);
+                Some((start, start + Duration::days(13)))
+            }
+            PaySchedule::SemiMonthly => {
+                let day = |day| NaiveDate::from_ymd_opt(date.year(), date.month(), day).unwrap();
+                if date.day() < 15 {
+                    Some((day(1), day(14)))
+                } else {
+                    Some((day(15), day(1) + Months::new(1) - Duration::days(1)))
+                }
+            }
+            PaySchedule::Custom(Paydays(paydays)) => {
+                // the first payday after `date`, which ends its period
+                let next = paydays.partition_point(|&payday| payday <= date);
+                if next == 0 || next == paydays.len() {
+                    return None;
+                }
+                Some((paydays[next - 1], paydays[next] - Duration::days(1)))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, month, day).unwrap()
+    }
+
+    #[test]
+    fn test_pay_periods() {
+        let biweekly = PaySchedule::Biweekly { anchor: date(1, 5) };
+        assert_eq!(
+            biweekly.range_containing(date(1, 20)),
+            Some((date(1, 19), date(2, 1)))
+        );
+        // periods repeat before the anchor too
+        assert_eq!(
+            biweekly.range_containing(date(1, 4)),
+            Some((date(12, 22) - Months::new(12), date(1, 4)))
+        );
+
+        let semi = PaySchedule::SemiMonthly;
+        assert_eq!(
+            semi.range_containing(date(2, 14)),
+            Some((date(2, 1), date(2, 14)))
+        );
+        assert_eq!(
+            semi.range_containing(date(2, 20)),
+            Some((date(2, 15), date(2, 29)))
+        );
+
+        let custom = PaySchedule::Custom(vec![date(3, 1), date(1, 10), date(2, 3)].into());
+        assert_eq!(
+            custom.range_containing(date(2, 3)),
+            Some((date(2, 3), date(2, 29)))
+        );
+        assert_eq!(custom.range_containing(date(1, 9)), None);
+        assert_eq!(custom.range_containing(date(3, 1)), None);
+
+        // kept sorted however they're added
+        let PaySchedule::Custom(mut paydays) = custom else {
+            unreachable!()
+        };
+        paydays.insert(date(1, 20));
+        paydays.insert(date(1, 20));
+        let days: Vec<NaiveDate> = paydays.iter().copied().collect();
+        assert_eq!(days, [date(1, 10), date(1, 20), date(2, 3), date(3, 1)]);
+        let read: Paydays = serde_json::from_str(r#"["2024-02-03", "2024-01-10"]"#).unwrap();
+        assert_eq!(read.iter().next(), Some(&date(1, 10)));
+    }
+}
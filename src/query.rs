@@ -0,0 +1,289 @@
+use crate::category::Category;
+use crate::entry::Entry;
+use crate::store::EntryKey;
+use chrono::NaiveDate;
+use regex::{Regex, RegexBuilder};
+use std::collections::BTreeMap;
+
+/// How an entry's name has to match a query. Both ignore case
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
+pub enum NameFilter {
+    Contains(String),
+    Regex(NameRegex),
+}
+
+/// A regex over entry names that ignores case. It's compiled once, when it's made, rather than every time a query
+/// is matched. Written out as its pattern
+#[derive(Clone, Debug)]
+pub struct NameRegex(Regex);
+
+impl NameRegex {
+    /// Fails if `pattern` isn't a valid regex
+    pub fn new(pattern: &str) -> Result<Self, regex::Error> {
+        Ok(Self(
+            RegexBuilder::new(pattern).case_insensitive(true).build()?,
+        ))
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+impl PartialEq for NameRegex {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl serde::Serialize for NameRegex {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for NameRegex {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let pattern = String::deserialize(deserializer)?;
+        NameRegex::new(&pattern).map_err(serde::de::Error::custom)
+    }
+}
+
+/// A filter over entries, built up from any number of conditions. Conditions left as None match everything, so
+/// the default query matches every entry
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)] // if we add new fields, give them default values when deserializing old state
+pub struct Query {
+    /// First and last day to match, inclusive
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub categories: Option<Vec<Category>>,
+    pub name: Option<NameFilter>,
+    /// Cost range to match, inclusive
+    pub min_cost: Option<f32>,
+    pub max_cost: Option<f32>,
+}
+
+impl Query {
+    pub fn between(mut self, from: NaiveDate, to: NaiveDate) -> Self {
+        self.from = Some(from);
+        self.to = Some(to);
+        self
+    }
+
+    /// Only match dates from `from` to `to` that the query already matched
+    pub fn within(mut self, from: NaiveDate, to: NaiveDate) -> Self {
+        self.from = Some(self.from.map_or(from, |current| current.max(from)));
+        self.to = Some(self.to.map_or(to, |current| current.min(to)));
+        self
+    }
+
+    /// Only match `category`. `Category::All` leaves the categories as they are
+    pub fn category(self, category: Category) -> Self {
+        match category {
+            Category::All => self,
+            category => self.categories(vec![category]),
+        }
+    }
+
+    /// Only match `categories`. If the query was already limited to some categories, only the ones in both match
+    pub fn categories(mut self, categories: Vec<Category>) -> Self {
+        self.categories = Some(match self.categories {
+            Some(current) => categories
+                .into_iter()
+                .filter(|category| current.contains(category))
+                .collect(),
+            None => categories,
+        });
+        self
+    }
+
+    /// Whether the category condition lets `category` through
+    pub fn includes_category(&self, category: Category) -> bool {
+        self.categories
+            .as_ref()
+            .map_or(true, |categories| categories.contains(&category))
+    }
+
+    /// Build the function that decides whether an entry matches
+    pub fn matcher(&self) -> impl Fn(&Entry) -> bool + '_ {
+        let name_regex = match &self.name {
+            Some(NameFilter::Regex(regex)) => Some(&regex.0),
+            _ => None,
+        };
+        let contains = match &self.name {
+            Some(NameFilter::Contains(text)) => Some(text.to_lowercase()),
+            _ => None,
+        };
+
+        move |entry: &Entry| {
+            let cost = f32::from(entry.cost);
+            self.from.map_or(true, |from| from <= entry.date)
+                && self.to.map_or(true, |to| entry.date <= to)
+                && self.includes_category(entry.category)
+                && self.min_cost.map_or(true, |min| min <= cost)
+                && self.max_cost.map_or(true, |max| cost <= max)
+                && contains
+                    .as_ref()
+                    .map_or(true, |text| entry.name.to_lowercase().contains(text))
+                && name_regex
+                    .as_ref()
+                    .map_or(true, |regex| regex.is_match(&entry.name))
+        }
+    }
+}
+
+/// The entries that matched a query, in the order they were checked, along with aggregates over them
+pub struct QueryResult<'a> {
+    matches: Vec<(EntryKey, &'a Entry)>,
+}
+
+impl<'a> QueryResult<'a> {
+    /// Match `query` against only `candidates`, keeping them in that order
+    pub fn from_candidates(
+        candidates: impl IntoIterator<Item = (EntryKey, &'a Entry)>,
+        query: &Query,
+    ) -> Self {
+        let matches = query.matcher();
+        let matches = candidates
+            .into_iter()
+            .filter(|(_, entry)| matches(entry))
+            .collect();
+        QueryResult { matches }
+    }
+
+    /// Each matching entry along with its key in the store
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (EntryKey, &'a Entry)> + '_ {
+        self.matches.iter().copied()
+    }
+
+    pub fn entries(&self) -> impl DoubleEndedIterator<Item = &'a Entry> + '_ {
+        self.iter().map(|(_, entry)| entry)
+    }
+
+    pub fn count(&self) -> usize {
+        self.matches.len()
+    }
+
+    pub fn total(&self) -> f32 {
+        self.entries().map(|entry| f32::from(entry.cost)).sum()
+    }
+
+    /// None if nothing matched
+    pub fn average(&self) -> Option<f32> {
+        (self.count() > 0).then(|| self.total() / self.count() as f32)
+    }
+
+    /// The total of the matching entries in each category
+    pub fn by_category(&self) -> BTreeMap<Category, f32> {
+        let mut totals = BTreeMap::new();
+        for entry in self.entries() {
+            *totals.entry(entry.category).or_default() += f32::from(entry.cost);
+        }
+        totals
+    }
+
+    /// The earliest and latest dates of the matching entries. None if nothing matched
+    pub fn date_range(&self) -> Option<(NaiveDate, NaiveDate)> {
+        let first = self.entries().map(|entry| entry.date).min()?;
+        let last = self.entries().map(|entry| entry.date).max()?;
+        Some((first, last))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entry::Cost;
+    use crate::organize::SortBy;
+    use crate::store::EntryStore;
+
+    fn entries() -> EntryStore {
+        [
+            ("Coffee", 4.5, 1, Category::OtherFood),
+            ("Groceries at Aldi", 62.0, 3, Category::Groceries),
+            ("coffee beans", 15.0, 9, Category::Groceries),
+            ("Gas", 40.0, 20, Category::Car),
+        ]
+        .into_iter()
+        .map(|(name, cost, day, category)| Entry {
+            name: name.to_string(),
+            cost: Cost::try_from(cost).unwrap(),
+            date: NaiveDate::from_ymd_opt(2023, 4, day).unwrap(),
+            category,
+            ..Default::default()
+        })
+        .collect::<Vec<_>>()
+        .into()
+    }
+
+    fn names(entries: &EntryStore, query: &Query) -> Vec<String> {
+        entries
+            .query(query, SortBy::Date)
+            .entries()
+            .map(|entry| entry.name.clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_query() {
+        let entries = entries();
+        let all = entries.query(&Query::default(), SortBy::Date);
+        assert_eq!(all.count(), 4);
+        assert_eq!(all.total(), 121.5);
+
+        let coffee = Query {
+            name: Some(NameFilter::Contains("COFFEE".to_string())),
+            ..Default::default()
+        };
+        assert_eq!(names(&entries, &coffee), ["Coffee", "coffee beans"]);
+        let groceries = coffee.clone().category(Category::Groceries);
+        assert_eq!(names(&entries, &groceries), ["coffee beans"]);
+
+        // narrowing the categories never adds one back
+        let narrowed = groceries.categories(vec![Category::Car, Category::OtherFood]);
+        assert!(names(&entries, &narrowed).is_empty());
+
+        let dates = |from, to| {
+            let date = |day| NaiveDate::from_ymd_opt(2023, 4, day).unwrap();
+            Query::default().between(date(from), date(to))
+        };
+        assert_eq!(names(&entries, &dates(3, 9)).len(), 2);
+        let date = |day| NaiveDate::from_ymd_opt(2023, 4, day).unwrap();
+        assert_eq!(
+            names(&entries, &dates(3, 30).within(date(1), date(5))),
+            ["Groceries at Aldi"]
+        );
+
+        let expensive = Query {
+            min_cost: Some(10.0),
+            ..dates(1, 30)
+        };
+        let result = entries.query(&expensive, SortBy::Date);
+        assert_eq!(result.average(), Some(39.0));
+        assert_eq!(result.by_category()[&Category::Groceries], 77.0);
+        assert_eq!(
+            result.date_range(),
+            Some((
+                NaiveDate::from_ymd_opt(2023, 4, 3).unwrap(),
+                NaiveDate::from_ymd_opt(2023, 4, 20).unwrap()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_regex() {
+        let entries = entries();
+        let regex = Query {
+            name: Some(NameFilter::Regex(NameRegex::new("^(gas|coffee)$").unwrap())),
+            ..Default::default()
+        };
+        assert_eq!(names(&entries, &regex), ["Coffee", "Gas"]);
+        assert!(NameRegex::new("(").is_err());
+
+        // written out as the pattern, and compiled again when read
+        let json = serde_json::to_string(&regex).unwrap();
+        assert_eq!(serde_json::from_str::<Query>(&json).unwrap(), regex);
+        assert!(serde_json::from_str::<NameRegex>("\"(\"").is_err());
+    }
+}
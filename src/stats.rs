@@ -0,0 +1,114 @@
+use crate::category::Category;
+use crate::entry::Entry;
+use std::io::Result as IoResult;
+
+/// The percentiles listed for each category, besides the median
+pub const PERCENTILES: [u32; 3] = [25, 75, 90];
+/// How many of the most expensive entries are kept for each category
+pub const LARGEST: usize = 3;
+
+/// Summary statistics over one category's entries during a period. `Category::All` covers every category
+#[derive(Debug, Clone, PartialEq)]
+pub struct CategoryStats {
+    pub category: Category,
+    pub total: f32,
+    pub count: usize,
+    pub mean: f32,
+    pub median: f32,
+    /// One for each of `PERCENTILES`
+    pub percentiles: Vec<f32>,
+    /// The most expensive entries, most expensive first
+    pub largest: Vec<Entry>,
+    /// The change from the month before to the last month of the period, as a fraction. None if nothing was
+    /// spent the month before
+    pub month_over_month: Option<f32>,
+    /// The change from the same period a year earlier, as a fraction. None if nothing was spent then
+    pub year_over_year: Option<f32>,
+    /// The total divided by how many groups the period spans
+    pub per_group: f32,
+}
+
+/// The value `p` percent of the way through `sorted`, interpolating between the two closest values. 0 if it's empty
+pub fn percentile(sorted: &[f32], p: f32) -> f32 {
+    let Some(&last) = sorted.last() else {
+        return 0.0;
+    };
+    let rank = p / 100.0 * (sorted.len() - 1) as f32;
+    let below = rank.floor() as usize;
+    let Some(&above) = sorted.get(below + 1) else {
+        return last;
+    };
+    sorted[below] + (above - sorted[below]) * rank.fract()
+}
+
+/// How much `current` changed from `previous`, as a fraction. None if `previous` is 0
+pub fn change(previous: f32, current: f32) -> Option<f32> {
+    (previous != 0.0).then_some((current - previous) / previous)
+}
+
+/// Write `stats` as csv, one row per category. `group` names what the per group average is over, e.g. "Month"
+pub fn write_stats_csv(stats: &[CategoryStats], group: &str) -> IoResult<String> {
+    let mut wtr = csv::Writer::from_writer(vec![]);
+
+    let mut header = vec![
+        "Category".to_string(),
+        "Total".to_string(),
+        "Count".to_string(),
+        "Mean".to_string(),
+        "Median".to_string(),
+    ];
+    header.extend(PERCENTILES.iter().map(|p| format!("P{p}")));
+    header.extend([
+        "Month over Month".to_string(),
+        "Year over Year".to_string(),
+        format!("Per {group}"),
+        "Largest".to_string(),
+    ]);
+    wtr.write_record(&header)?;
+
+    let change =
+        |change: Option<f32>| change.map_or(String::new(), |c| format!("{:.1}%", c * 100.0));
+    for stat in stats {
+        let mut record = vec![
+            stat.category.to_string(),
+            format!("{:.2}", stat.total),
+            stat.count.to_string(),
+            format!("{:.2}", stat.mean),
+            format!("{:.2}", stat.median),
+        ];
+        record.extend(stat.percentiles.iter().map(|value| format!("{value:.2}")));
+        record.extend([
+            change(stat.month_over_month),
+            change(stat.year_over_year),
+            format!("{:.2}", stat.per_group),
+            stat.largest
+                .iter()
+                .map(|entry| format!("{} ({:.2})", entry.name, f32::from(entry.cost)))
+                .collect::<Vec<_>>()
+                .join("; "),
+        ]);
+        wtr.write_record(&record)?;
+    }
+
+    let bytes = wtr.into_inner().map_err(|e| e.into_error())?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentile() {
+        let sorted = [10.0, 20.0, 30.0, 40.0, 50.0];
+        assert_eq!(percentile(&sorted, 50.0), 30.0);
+        assert_eq!(percentile(&sorted, 0.0), 10.0);
+        assert_eq!(percentile(&sorted, 100.0), 50.0);
+        assert_eq!(percentile(&sorted, 75.0), 40.0);
+        assert_eq!(percentile(&[10.0, 20.0], 50.0), 15.0);
+        assert_eq!(percentile(&[], 50.0), 0.0);
+
+        assert_eq!(change(50.0, 75.0), Some(0.5));
+        assert_eq!(change(0.0, 75.0), None);
+    }
+}
@@ -0,0 +1,293 @@
+use crate::category::Category;
+use crate::entry::Entry;
+use crate::organize::SortBy;
+use crate::query::{Query, QueryResult};
+use chrono::NaiveDate;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::Bound;
+
+/// Identifies an entry in the store: its date, then when it was added relative to other entries on the same day.
+/// An entry keeps its key for as long as it's in the store, however many entries are added or removed around it
+pub type EntryKey = (NaiveDate, u64);
+
+/// A cost that can be ordered, so entries can be indexed by it
+#[derive(Clone, Copy, PartialEq)]
+struct CostOrd(f32);
+
+impl Eq for CostOrd {}
+
+impl PartialOrd for CostOrd {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for CostOrd {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+/// Every entry, kept in date order (entries on the same day stay in the order they were added).
+///
+/// Entries are also indexed by category and by cost, and the total of each day is kept, so sorted views, range
+/// lookups and totals over a period don't have to go through every entry. Adding or removing an entry only updates
+/// the parts of the indexes it's in
+#[derive(Default)]
+pub struct EntryStore {
+    entries: BTreeMap<EntryKey, Entry>,
+    // the second half of the next key, so entries added on the same day sort in the order they were added
+    next_seq: u64,
+    by_category: BTreeMap<Category, BTreeSet<EntryKey>>,
+    // cheapest first
+    by_cost: BTreeSet<(CostOrd, EntryKey)>,
+    // what was spent each day in each category, plus every category under `Category::All`. Kept in f64 so adding
+    // and removing entries doesn't drift
+    daily: BTreeMap<Category, BTreeMap<NaiveDate, f64>>,
+}
+
+impl From<Vec<Entry>> for EntryStore {
+    fn from(entries: Vec<Entry>) -> Self {
+        let mut store = EntryStore::default();
+        for entry in entries {
+            store.insert(entry);
+        }
+        store
+    }
+}
+
+impl<'a> IntoIterator for &'a EntryStore {
+    type Item = &'a Entry;
+    type IntoIter = std::collections::btree_map::Values<'a, EntryKey, Entry>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.values()
+    }
+}
+
+impl EntryStore {
+    /// Add `entry` after any others on the same day. Returns its key
+    pub fn insert(&mut self, entry: Entry) -> EntryKey {
+        let key = (entry.date, self.next_seq);
+        self.next_seq += 1;
+
+        let cost = f32::from(entry.cost);
+        self.by_category
+            .entry(entry.category)
+            .or_default()
+            .insert(key);
+        self.by_cost.insert((CostOrd(cost), key));
+        self.add_daily(entry.category, entry.date, cost as f64);
+        self.entries.insert(key, entry);
+        key
+    }
+
+    /// Take the entry with `key` out of the store. None if there isn't one
+    pub fn remove(&mut self, key: EntryKey) -> Option<Entry> {
+        let entry = self.entries.remove(&key)?;
+
+        let cost = f32::from(entry.cost);
+        if let Some(keys) = self.by_category.get_mut(&entry.category) {
+            keys.remove(&key);
+            if keys.is_empty() {
+                self.by_category.remove(&entry.category);
+            }
+        }
+        self.by_cost.remove(&(CostOrd(cost), key));
+        self.add_daily(entry.category, entry.date, -(cost as f64));
+        Some(entry)
+    }
+
+    pub fn get(&self, key: EntryKey) -> Option<&Entry> {
+        self.entries.get(&key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Every entry along with its key, in date order
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (EntryKey, &Entry)> + '_ {
+        self.entries.iter().map(|(&key, entry)| (key, entry))
+    }
+
+    /// Every entry, in date order
+    pub fn entries(&self) -> impl DoubleEndedIterator<Item = &Entry> + '_ {
+        self.entries.values()
+    }
+
+    /// The earliest and latest days anything was spent on. None if there are no entries
+    pub fn date_range(&self) -> Option<(NaiveDate, NaiveDate)> {
+        let (first, _) = self.entries.first_key_value()?;
+        let (last, _) = self.entries.last_key_value()?;
+        Some((first.0, last.0))
+    }
+
+    /// The first day anything in `category` was spent on. `Category::All` counts every category
+    pub fn first_date(&self, category: Category) -> Option<NaiveDate> {
+        match category {
+            Category::All => self.entries.first_key_value().map(|(key, _)| key.0),
+            category => self.by_category.get(&category)?.first().map(|key| key.0),
+        }
+    }
+
+    /// The total spent in `category` from `from` to `to`, inclusive. `Category::All` counts every category
+    pub fn total(&self, category: Category, from: NaiveDate, to: NaiveDate) -> f32 {
+        match self.daily.get(&category) {
+            Some(days) if from <= to => {
+                days.range(from..=to).map(|(_, total)| total).sum::<f64>() as f32
+            }
+            _ => 0.0,
+        }
+    }
+
+    /// The entries that match `query`, listed in `sort_by` order. Only the entries in the query's dates and
+    /// categories are checked against the rest of it
+    pub fn query(&self, query: &Query, sort_by: SortBy) -> QueryResult<'_> {
+        let Some(span) = key_span(query.from, query.to) else {
+            return QueryResult::from_candidates(std::iter::empty(), query);
+        };
+
+        if sort_by == SortBy::Cost
+            && query.categories.is_none()
+            && span == (Bound::Unbounded, Bound::Unbounded)
+        {
+            // every entry is a candidate, and by_cost already has them in order
+            let candidates = self
+                .by_cost
+                .iter()
+                .map(|(_, key)| (*key, &self.entries[key]));
+            return QueryResult::from_candidates(candidates, query);
+        }
+
+        let mut candidates: Vec<(EntryKey, &Entry)> = match &query.categories {
+            Some(categories) => {
+                let mut keys: Vec<EntryKey> = self
+                    .by_category
+                    .iter()
+                    .filter(|(category, _)| categories.contains(category))
+                    .flat_map(|(_, keys)| keys.range(span).copied())
+                    .collect();
+                keys.sort_unstable();
+                keys.into_iter()
+                    .map(|key| (key, &self.entries[&key]))
+                    .collect()
+            }
+            None => self
+                .entries
+                .range(span)
+                .map(|(&key, entry)| (key, entry))
+                .collect(),
+        };
+
+        if sort_by == SortBy::Cost {
+            // a stable sort, so entries that cost the same stay in date order like they do in by_cost
+            candidates.sort_by(|(_, a), (_, b)| f32::from(a.cost).total_cmp(&f32::from(b.cost)));
+        }
+        QueryResult::from_candidates(candidates, query)
+    }
+
+    fn add_daily(&mut self, category: Category, date: NaiveDate, cost: f64) {
+        for category in [category, Category::All] {
+            *self
+                .daily
+                .entry(category)
+                .or_default()
+                .entry(date)
+                .or_default() += cost;
+        }
+    }
+}
+
+/// The keys of every entry from `from` to `to`, inclusive. Either end can be left open. None if `from` is after `to`
+fn key_span(
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+) -> Option<(Bound<EntryKey>, Bound<EntryKey>)> {
+    if let (Some(from), Some(to)) = (from, to) {
+        if from > to {
+            return None;
+        }
+    }
+    Some((
+        from.map_or(Bound::Unbounded, |from| Bound::Included((from, 0))),
+        to.map_or(Bound::Unbounded, |to| Bound::Included((to, u64::MAX))),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entry::Cost;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2023, 6, day).unwrap()
+    }
+
+    fn store() -> EntryStore {
+        [
+            ("b", 30.0, 9, Category::Groceries),
+            ("a", 10.0, 2, Category::Car),
+            ("c", 5.0, 9, Category::Groceries),
+            ("d", 20.0, 20, Category::Car),
+        ]
+        .into_iter()
+        .map(|(name, cost, day, category)| Entry {
+            name: name.to_string(),
+            cost: Cost::try_from(cost).unwrap(),
+            date: date(day),
+            category,
+            ..Default::default()
+        })
+        .collect::<Vec<_>>()
+        .into()
+    }
+
+    fn names(result: QueryResult<'_>) -> String {
+        result.entries().map(|entry| entry.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_store() {
+        let mut store = store();
+        assert_eq!(store.date_range(), Some((date(2), date(20))));
+        assert_eq!(store.first_date(Category::Groceries), Some(date(9)));
+        assert_eq!(store.first_date(Category::Clothes), None);
+        assert_eq!(names(store.query(&Query::default(), SortBy::Date)), "abcd");
+        assert_eq!(names(store.query(&Query::default(), SortBy::Cost)), "cadb");
+
+        let june = Query::default().between(date(3), date(20));
+        assert_eq!(names(store.query(&june, SortBy::Date)), "bcd");
+        assert_eq!(names(store.query(&june, SortBy::Cost)), "cdb");
+        let groceries = june.category(Category::Groceries);
+        assert_eq!(names(store.query(&groceries, SortBy::Cost)), "cb");
+        // an empty range, rather than a backwards one
+        let backwards = Query::default().between(date(20), date(3));
+        assert_eq!(names(store.query(&backwards, SortBy::Date)), "");
+
+        assert_eq!(store.total(Category::All, date(1), date(9)), 45.0);
+        assert_eq!(store.total(Category::Car, date(3), date(30)), 20.0);
+        assert_eq!(store.total(Category::Clothes, date(1), date(30)), 0.0);
+        assert_eq!(store.total(Category::All, date(21), date(30)), 0.0);
+
+        // added after the other entries on the same day, and other entries keep their keys
+        let (first, _) = store.iter().next().unwrap();
+        let key = store.insert(Entry {
+            name: "e".to_string(),
+            date: date(9),
+            ..Default::default()
+        });
+        assert_eq!(store.get(key).unwrap().name, "e");
+        assert_eq!(store.remove(first).unwrap().name, "a");
+        assert!(store.remove(first).is_none());
+        assert_eq!(names(store.query(&Query::default(), SortBy::Date)), "bced");
+        assert_eq!(names(store.query(&Query::default(), SortBy::Cost)), "ecdb");
+        assert_eq!(store.total(Category::All, date(1), date(30)), 55.0);
+        assert_eq!(store.total(Category::Car, date(1), date(30)), 20.0);
+    }
+}
@@ -0,0 +1,280 @@
+use crate::category::Category;
+use crate::entry::Entry;
+use crate::stats::percentile;
+use chrono::{Duration, Months, NaiveDate};
+use std::collections::BTreeMap;
+
+/// A charge has to come at least this many times before it counts as a subscription
+const MIN_CHARGES: usize = 3;
+
+/// How often a subscription charges
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cadence {
+    Weekly,
+    Monthly,
+    Quarterly,
+    Yearly,
+}
+
+impl std::fmt::Display for Cadence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Cadence::Weekly => write!(f, "Weekly"),
+            Cadence::Monthly => write!(f, "Monthly"),
+            Cadence::Quarterly => write!(f, "Quarterly"),
+            Cadence::Yearly => write!(f, "Yearly"),
+        }
+    }
+}
+
+impl Cadence {
+    /// The cadence charges `days` apart come at, allowing for months of different lengths and charges that come
+    /// a few days early or late
+    fn from_gap(days: i64) -> Option<Cadence> {
+        match days {
+            6..=8 => Some(Cadence::Weekly),
+            26..=35 => Some(Cadence::Monthly),
+            85..=98 => Some(Cadence::Quarterly),
+            355..=375 => Some(Cadence::Yearly),
+            _ => None,
+        }
+    }
+
+    /// When the charge after one on `date` is due
+    pub fn next(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Cadence::Weekly => date + Duration::days(7),
+            Cadence::Monthly => date + Months::new(1),
+            Cadence::Quarterly => date + Months::new(3),
+            Cadence::Yearly => date + Months::new(12),
+        }
+    }
+
+    pub fn per_year(&self) -> f32 {
+        match self {
+            Cadence::Weekly => 52.0,
+            Cadence::Monthly => 12.0,
+            Cadence::Quarterly => 4.0,
+            Cadence::Yearly => 1.0,
+        }
+    }
+}
+
+/// The part of a charge's name that stays the same from one charge to the next: lowercase, without the digits
+/// and punctuation banks add, like "NETFLIX.COM #4921" -> "netflix com"
+pub fn normalize(name: &str) -> String {
+    name.to_lowercase()
+        .split(|c: char| !c.is_alphabetic())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// A charge that came regularly enough, for a stable enough amount, to be a subscription
+#[derive(Debug, Clone, PartialEq)]
+pub struct Subscription {
+    /// The normalized name its charges share
+    pub key: String,
+    /// The name of its latest charge, as written
+    pub name: String,
+    pub category: Category,
+    pub cadence: Cadence,
+    /// What the latest charge cost
+    pub amount: f32,
+    /// The day of the latest charge
+    pub last: NaiveDate,
+    /// The day of the first charge and each one after that cost something different, along with the new price
+    pub prices: Vec<(NaiveDate, f32)>,
+}
+
+impl Subscription {
+    /// When the next charge is due
+    pub fn next_expected(&self) -> NaiveDate {
+        self.cadence.next(self.last)
+    }
+
+    /// What a year of it costs at the current price
+    pub fn annual_cost(&self) -> f32 {
+        self.amount * self.cadence.per_year()
+    }
+
+    /// Whether `entry` is one of its charges
+    pub fn matches(&self, entry: &Entry) -> bool {
+        entry.category == self.category && normalize(&entry.name) == self.key
+    }
+}
+
+/// Whether a confirmed subscription is still being paid for
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleStatus {
+    Active,
+    /// Its charges have stopped on purpose, so a missing one isn't flagged
+    Cancelled,
+}
+
+impl std::fmt::Display for RuleStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            RuleStatus::Active => write!(f, "Confirmed"),
+            RuleStatus::Cancelled => write!(f, "Cancelled"),
+        }
+    }
+}
+
+/// A subscription the user confirmed, or marked as cancelled
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq)]
+pub struct RecurringRule {
+    /// The normalized name of its charges
+    pub key: String,
+    pub name: String,
+    pub category: Category,
+    pub cadence: Cadence,
+    pub amount: f32,
+    pub status: RuleStatus,
+}
+
+impl RecurringRule {
+    pub fn new(subscription: &Subscription, status: RuleStatus) -> Self {
+        Self {
+            key: subscription.key.clone(),
+            name: subscription.name.clone(),
+            category: subscription.category,
+            cadence: subscription.cadence,
+            amount: subscription.amount,
+            status,
+        }
+    }
+
+    /// Whether `entry` is one of its charges
+    pub fn matches(&self, entry: &Entry) -> bool {
+        entry.category == self.category && normalize(&entry.name) == self.key
+    }
+
+    /// Whether it was made from `subscription`
+    pub fn is_for(&self, subscription: &Subscription) -> bool {
+        self.category == subscription.category && self.key == subscription.key
+    }
+}
+
+/// Find the subscriptions among `entries` (in date order): charges in one category with the same normalized name
+/// that came at least `MIN_CHARGES` times, always about the same time apart, and changed price at most every other
+/// time. Sorted by what they cost a year, most first
+pub fn detect<'a>(entries: impl IntoIterator<Item = &'a Entry>) -> Vec<Subscription> {
+    let mut charges: BTreeMap<(String, Category), Vec<&Entry>> = BTreeMap::new();
+    for entry in entries {
+        charges
+            .entry((normalize(&entry.name), entry.category))
+            .or_default()
+            .push(entry);
+    }
+
+    let mut subscriptions: Vec<Subscription> = charges
+        .into_iter()
+        .filter(|(_, charges)| charges.len() >= MIN_CHARGES)
+        .filter_map(|((key, category), charges)| {
+            let gaps: Vec<i64> = charges
+                .windows(2)
+                .map(|pair| (pair[1].date - pair[0].date).num_days())
+                .collect();
+            let mut sorted: Vec<f32> = gaps.iter().map(|&gap| gap as f32).collect();
+            sorted.sort_by(f32::total_cmp);
+            let cadence = Cadence::from_gap(percentile(&sorted, 50.0).round() as i64)?;
+            if gaps
+                .iter()
+                .any(|&gap| Cadence::from_gap(gap) != Some(cadence))
+            {
+                return None;
+            }
+
+            let mut prices: Vec<(NaiveDate, f32)> = vec![];
+            for charge in &charges {
+                let cost = f32::from(charge.cost);
+                if prices.last().map_or(true, |&(_, price)| price != cost) {
+                    prices.push((charge.date, cost));
+                }
+            }
+            if (prices.len() - 1) * 2 > gaps.len() {
+                return None;
+            }
+
+            let last = charges.last()?;
+            Some(Subscription {
+                key,
+                name: last.name.clone(),
+                category,
+                cadence,
+                amount: f32::from(last.cost),
+                last: last.date,
+                prices,
+            })
+        })
+        .collect();
+    subscriptions.sort_by(|a, b| b.annual_cost().total_cmp(&a.annual_cost()));
+    subscriptions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entry::Cost;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn test_detect() {
+        let mut entries = vec![];
+        let mut add = |name: &str, cost: f32, date, category| {
+            entries.push(Entry {
+                name: name.to_string(),
+                cost: Cost::try_from(cost).unwrap(),
+                date,
+                category,
+                ..Default::default()
+            })
+        };
+        for (month, day, cost) in [(1, 5, 15.0), (2, 4, 15.0), (3, 6, 15.0), (4, 5, 18.0)] {
+            add(
+                &format!("NETFLIX.COM #{month}"),
+                cost,
+                date(2023, month, day),
+                Category::Subscriptions,
+            );
+        }
+        for year in 2021..=2023 {
+            add("Costco Membership", 60.0, date(year, 7, 1), Category::Misc);
+        }
+        // regular, but a different price every time
+        for month in 1..=4 {
+            add(
+                "Aldi",
+                40.0 + month as f32,
+                date(2023, month, 2),
+                Category::Groceries,
+            );
+        }
+        // the same price, but not regular
+        for (month, day) in [(1, 1), (1, 20), (3, 1)] {
+            add("Gas", 35.0, date(2023, month, day), Category::Car);
+        }
+        entries.sort_by_key(|entry| entry.date);
+
+        let subscriptions = detect(&entries);
+        assert_eq!(subscriptions.len(), 2);
+
+        let netflix = &subscriptions[0];
+        assert_eq!(netflix.key, "netflix com");
+        assert_eq!(netflix.cadence, Cadence::Monthly);
+        assert_eq!(netflix.next_expected(), date(2023, 5, 5));
+        assert_eq!(netflix.annual_cost(), 216.0);
+        assert_eq!(
+            netflix.prices,
+            vec![(date(2023, 1, 5), 15.0), (date(2023, 4, 5), 18.0)]
+        );
+
+        let costco = &subscriptions[1];
+        assert_eq!(costco.cadence, Cadence::Yearly);
+        assert_eq!(costco.next_expected(), date(2024, 7, 1));
+    }
+}